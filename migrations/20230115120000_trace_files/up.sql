@@ -0,0 +1,8 @@
+CREATE TABLE trace_files (
+    path TEXT NOT NULL PRIMARY KEY,
+    object TEXT NOT NULL,
+    size BIGINT NOT NULL,
+    mtime BIGINT NOT NULL,
+    hash TEXT NOT NULL,
+    status TEXT NOT NULL
+);
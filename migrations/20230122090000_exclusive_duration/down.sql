@@ -0,0 +1,5 @@
+ALTER TABLE source DROP COLUMN exclusive_duration;
+ALTER TABLE parse_class DROP COLUMN exclusive_duration;
+ALTER TABLE parse_template DROP COLUMN exclusive_duration;
+ALTER TABLE instantiate_class DROP COLUMN exclusive_duration;
+ALTER TABLE instantiate_function DROP COLUMN exclusive_duration;
@@ -0,0 +1,14 @@
+-- Rows ingested so far have no exclusive time, drop them so the next run ingests everything again
+DELETE FROM objects;
+DELETE FROM trace_files;
+DELETE FROM source;
+DELETE FROM parse_class;
+DELETE FROM parse_template;
+DELETE FROM instantiate_class;
+DELETE FROM instantiate_function;
+
+ALTER TABLE source ADD COLUMN exclusive_duration INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE parse_class ADD COLUMN exclusive_duration INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE parse_template ADD COLUMN exclusive_duration INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE instantiate_class ADD COLUMN exclusive_duration INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE instantiate_function ADD COLUMN exclusive_duration INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+DROP TABLE include_edges;
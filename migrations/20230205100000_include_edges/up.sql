@@ -0,0 +1,22 @@
+-- Objects ingested so far have no include edges, drop them so the next run ingests everything again
+DELETE FROM objects;
+DELETE FROM trace_files;
+DELETE FROM source;
+DELETE FROM parse_class;
+DELETE FROM parse_template;
+DELETE FROM instantiate_class;
+DELETE FROM instantiate_function;
+
+CREATE TABLE include_edges (
+    id INTEGER NOT NULL PRIMARY KEY,
+    object TEXT NOT NULL,
+    includer TEXT,
+    included TEXT NOT NULL,
+    depth INTEGER NOT NULL,
+    duration INTEGER NOT NULL,
+    exclusive_duration INTEGER NOT NULL,
+    count INTEGER NOT NULL
+);
+
+CREATE INDEX include_edges_object ON include_edges (object);
+CREATE INDEX include_edges_included ON include_edges (included);
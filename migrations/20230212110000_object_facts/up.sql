@@ -0,0 +1,22 @@
+-- Objects ingested so far have no facts to retract them with, drop them so the next run ingests
+-- everything again
+DELETE FROM objects;
+DELETE FROM trace_files;
+DELETE FROM source;
+DELETE FROM parse_class;
+DELETE FROM parse_template;
+DELETE FROM instantiate_class;
+DELETE FROM instantiate_function;
+DELETE FROM include_edges;
+
+CREATE TABLE object_facts (
+    object TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    name TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    exclusive_duration INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (object, kind, name)
+);
+
+CREATE INDEX object_facts_kind_name ON object_facts (kind, name);
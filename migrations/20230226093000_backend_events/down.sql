@@ -0,0 +1,3 @@
+DROP TABLE codegen_function;
+DROP TABLE opt_function;
+DROP TABLE backend_phase;
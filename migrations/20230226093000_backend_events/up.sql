@@ -0,0 +1,32 @@
+-- Objects ingested so far have no backend events, drop them so the next run ingests everything
+-- again
+DELETE FROM objects;
+DELETE FROM trace_files;
+DELETE FROM source;
+DELETE FROM parse_class;
+DELETE FROM parse_template;
+DELETE FROM instantiate_class;
+DELETE FROM instantiate_function;
+DELETE FROM include_edges;
+DELETE FROM object_facts;
+
+CREATE TABLE codegen_function (
+    name TEXT NOT NULL PRIMARY KEY,
+    duration INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    exclusive_duration INTEGER NOT NULL
+);
+
+CREATE TABLE opt_function (
+    name TEXT NOT NULL PRIMARY KEY,
+    duration INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    exclusive_duration INTEGER NOT NULL
+);
+
+CREATE TABLE backend_phase (
+    name TEXT NOT NULL PRIMARY KEY,
+    duration INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    exclusive_duration INTEGER NOT NULL
+);
@@ -0,0 +1,2 @@
+DROP TABLE trace_metadata;
+DROP TABLE trace_threads;
@@ -0,0 +1,29 @@
+-- Objects ingested so far have no metadata, drop them so the next run ingests everything again
+DELETE FROM objects;
+DELETE FROM trace_files;
+DELETE FROM source;
+DELETE FROM parse_class;
+DELETE FROM parse_template;
+DELETE FROM instantiate_class;
+DELETE FROM instantiate_function;
+DELETE FROM codegen_function;
+DELETE FROM opt_function;
+DELETE FROM backend_phase;
+DELETE FROM include_edges;
+DELETE FROM object_facts;
+
+CREATE TABLE trace_metadata (
+    object TEXT NOT NULL PRIMARY KEY,
+    compiler TEXT,
+    process_name TEXT,
+    beginning_of_time BIGINT,
+    display_time_unit TEXT
+);
+
+CREATE TABLE trace_threads (
+    object TEXT NOT NULL,
+    pid BIGINT NOT NULL,
+    tid BIGINT NOT NULL,
+    name TEXT NOT NULL,
+    PRIMARY KEY (object, pid, tid)
+);
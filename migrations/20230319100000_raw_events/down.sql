@@ -0,0 +1,3 @@
+DROP TABLE raw_events;
+DROP TABLE raw_event_objects;
+ALTER TABLE trace_files DROP COLUMN raw_events;
@@ -0,0 +1,23 @@
+ALTER TABLE trace_files ADD COLUMN raw_events BOOLEAN NOT NULL DEFAULT 0;
+
+CREATE TABLE raw_event_objects (
+    id INTEGER NOT NULL PRIMARY KEY,
+    object TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE raw_events (
+    id INTEGER NOT NULL PRIMARY KEY,
+    object_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    category TEXT,
+    phase TEXT NOT NULL,
+    ts BIGINT NOT NULL,
+    dur BIGINT,
+    pid BIGINT NOT NULL,
+    tid BIGINT NOT NULL,
+    detail TEXT,
+    args TEXT
+);
+
+CREATE INDEX raw_events_object ON raw_events (object_id);
+CREATE INDEX raw_events_name ON raw_events (name);
@@ -0,0 +1,3 @@
+DROP TABLE object_aggregates;
+DROP TABLE aggregates;
+ALTER TABLE trace_files DROP COLUMN aggregates;
@@ -0,0 +1,20 @@
+ALTER TABLE trace_files ADD COLUMN aggregates TEXT NOT NULL DEFAULT '';
+
+CREATE TABLE aggregates (
+    aggregate TEXT NOT NULL,
+    name TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    exclusive_duration INTEGER NOT NULL,
+    PRIMARY KEY (aggregate, name)
+);
+
+CREATE TABLE object_aggregates (
+    object TEXT NOT NULL,
+    aggregate TEXT NOT NULL,
+    name TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    exclusive_duration INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (object, aggregate, name)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE objects ADD COLUMN partial BOOLEAN NOT NULL DEFAULT 0;
+-- Truncated traces were recorded as errors, forget them so the next run can salvage them
+DELETE FROM trace_files WHERE status = 'error';
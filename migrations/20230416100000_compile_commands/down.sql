@@ -0,0 +1 @@
+DROP TABLE compile_commands;
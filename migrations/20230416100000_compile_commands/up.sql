@@ -0,0 +1,9 @@
+CREATE TABLE compile_commands (
+    object TEXT NOT NULL PRIMARY KEY,
+    source TEXT NOT NULL,
+    output TEXT NOT NULL,
+    directory TEXT NOT NULL,
+    command TEXT NOT NULL
+);
+
+CREATE INDEX compile_commands_source ON compile_commands (source);
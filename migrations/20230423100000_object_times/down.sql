@@ -0,0 +1,2 @@
+ALTER TABLE objects DROP COLUMN end_time;
+ALTER TABLE objects DROP COLUMN start_time;
@@ -0,0 +1,22 @@
+ALTER TABLE objects ADD COLUMN start_time BIGINT;
+ALTER TABLE objects ADD COLUMN end_time BIGINT;
+-- Objects ingested so far have no start and end time, drop them so the next run ingests
+-- everything again
+DELETE FROM objects;
+DELETE FROM trace_files;
+DELETE FROM source;
+DELETE FROM parse_class;
+DELETE FROM parse_template;
+DELETE FROM instantiate_class;
+DELETE FROM instantiate_function;
+DELETE FROM codegen_function;
+DELETE FROM opt_function;
+DELETE FROM backend_phase;
+DELETE FROM include_edges;
+DELETE FROM object_facts;
+DELETE FROM trace_metadata;
+DELETE FROM trace_threads;
+DELETE FROM raw_events;
+DELETE FROM raw_event_objects;
+DELETE FROM aggregates;
+DELETE FROM object_aggregates;
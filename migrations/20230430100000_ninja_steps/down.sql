@@ -0,0 +1 @@
+DROP TABLE ninja_steps;
@@ -0,0 +1,10 @@
+CREATE TABLE ninja_steps (
+    output TEXT NOT NULL PRIMARY KEY,
+    start_ms BIGINT NOT NULL,
+    end_ms BIGINT NOT NULL,
+    mtime BIGINT NOT NULL,
+    command_hash TEXT NOT NULL,
+    object TEXT
+);
+
+CREATE INDEX ninja_steps_object ON ninja_steps (object);
@@ -0,0 +1,3 @@
+DROP TABLE thinlto_modules;
+DROP TABLE link_phases;
+DROP TABLE links;
@@ -0,0 +1,44 @@
+CREATE TABLE links (
+    path TEXT NOT NULL PRIMARY KEY,
+    total_time INTEGER NOT NULL,
+    partial BOOLEAN NOT NULL DEFAULT 0,
+    start_time BIGINT,
+    end_time BIGINT
+);
+
+CREATE TABLE link_phases (
+    link TEXT NOT NULL,
+    name TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (link, name)
+);
+
+CREATE TABLE thinlto_modules (
+    link TEXT NOT NULL,
+    module TEXT NOT NULL,
+    duration INTEGER NOT NULL,
+    count INTEGER NOT NULL,
+    PRIMARY KEY (link, module)
+);
+
+-- Linker traces ingested so far were stored as objects, drop the traces so the next run
+-- ingests them again as links
+DELETE FROM objects;
+DELETE FROM trace_files;
+DELETE FROM source;
+DELETE FROM parse_class;
+DELETE FROM parse_template;
+DELETE FROM instantiate_class;
+DELETE FROM instantiate_function;
+DELETE FROM codegen_function;
+DELETE FROM opt_function;
+DELETE FROM backend_phase;
+DELETE FROM include_edges;
+DELETE FROM object_facts;
+DELETE FROM trace_metadata;
+DELETE FROM trace_threads;
+DELETE FROM raw_events;
+DELETE FROM raw_event_objects;
+DELETE FROM aggregates;
+DELETE FROM object_aggregates;
@@ -0,0 +1 @@
+DROP TABLE cargo_units;
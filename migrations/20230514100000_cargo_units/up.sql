@@ -0,0 +1,10 @@
+CREATE TABLE cargo_units (
+    name TEXT NOT NULL,
+    version TEXT NOT NULL,
+    target TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    start_time BIGINT,
+    duration BIGINT NOT NULL,
+    rmeta_time BIGINT,
+    PRIMARY KEY (name, version, target, mode)
+);
@@ -0,0 +1 @@
+DROP TABLE gcc_passes;
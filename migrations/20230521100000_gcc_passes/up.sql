@@ -0,0 +1,9 @@
+CREATE TABLE gcc_passes (
+    object TEXT NOT NULL,
+    name TEXT NOT NULL,
+    usr INTEGER NOT NULL,
+    sys INTEGER NOT NULL,
+    wall INTEGER NOT NULL,
+    ggc BIGINT,
+    PRIMARY KEY (object, name)
+);
@@ -0,0 +1 @@
+ALTER TABLE ninja_steps DROP COLUMN build;
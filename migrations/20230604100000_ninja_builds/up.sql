@@ -0,0 +1 @@
+ALTER TABLE ninja_steps ADD COLUMN build BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE trace_metadata DROP COLUMN compiler_version;
@@ -0,0 +1,26 @@
+-- Objects ingested so far have no compiler version, drop them so the next run ingests everything
+-- again
+DELETE FROM objects;
+DELETE FROM links;
+DELETE FROM link_phases;
+DELETE FROM thinlto_modules;
+DELETE FROM trace_files;
+DELETE FROM source;
+DELETE FROM parse_class;
+DELETE FROM parse_template;
+DELETE FROM instantiate_class;
+DELETE FROM instantiate_function;
+DELETE FROM codegen_function;
+DELETE FROM opt_function;
+DELETE FROM backend_phase;
+DELETE FROM include_edges;
+DELETE FROM gcc_passes;
+DELETE FROM object_facts;
+DELETE FROM aggregates;
+DELETE FROM object_aggregates;
+DELETE FROM trace_metadata;
+DELETE FROM trace_threads;
+DELETE FROM raw_events;
+DELETE FROM raw_event_objects;
+
+ALTER TABLE trace_metadata ADD COLUMN compiler_version TEXT;
@@ -0,0 +1,156 @@
+/// Mapping of trace events to user defined aggregates, e.g.
+/// `{"aggregates": [{"aggregate": "by_file", "name": "Frontend", "key": "args.detail"}]}`
+extern crate serde;
+extern crate serde_json;
+
+use std::{borrow::Cow, error::Error, fs::File, io::BufReader, path::Path};
+
+use serde::Deserialize;
+
+use crate::{provenance::bytes_hash, trace_event::TraceEvent};
+
+/// Which part of an event an aggregate is keyed by
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum KeyField {
+    #[default]
+    Name,
+    Category,
+    Arg(String),
+}
+
+impl TryFrom<String> for KeyField {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "name" => Ok(KeyField::Name),
+            "category" | "cat" => Ok(KeyField::Category),
+            _ => match value.strip_prefix("args.") {
+                Some(field) if !field.is_empty() => Ok(KeyField::Arg(field.to_string())),
+                _ => Err(format!(
+                    "Unknown key {}, expected name, category or args.<field>",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AggregateRule {
+    /// Name the matching events are aggregated under
+    pub aggregate: String,
+    /// Event name to match
+    pub name: Option<String>,
+    /// Event category to match
+    #[serde(alias = "cat")]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub key: KeyField,
+}
+
+impl AggregateRule {
+    // Option::is_none_or needs Rust 1.82
+    #[allow(clippy::unnecessary_map_or)]
+    pub fn matches(&self, trace_event: &TraceEvent) -> bool {
+        self.name
+            .as_ref()
+            .map_or(true, |name| *name == trace_event.name)
+            && self.category.as_ref().map_or(true, |category| {
+                Some(category) == trace_event.category.as_ref()
+            })
+    }
+
+    /// The key of a matching event, `None` if the event does not have the key field
+    pub fn key<'a>(&self, trace_event: &'a TraceEvent) -> Option<Cow<'a, str>> {
+        match &self.key {
+            KeyField::Name => Some(Cow::Borrowed(trace_event.name.as_str())),
+            KeyField::Category => trace_event.category.as_deref().map(Cow::Borrowed),
+            KeyField::Arg(field) => match trace_event.args.as_ref()?.get(field)? {
+                serde_json::Value::String(value) => Some(Cow::Borrowed(value.as_str())),
+                serde_json::Value::Null => None,
+                value => Some(Cow::Owned(value.to_string())),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AggregateConfig {
+    pub aggregates: Vec<AggregateRule>,
+}
+
+impl AggregateConfig {
+    pub fn load(path: &Path) -> Result<AggregateConfig, Box<dyn Error + Send + Sync + 'static>> {
+        let reader = BufReader::new(File::open(path)?);
+        let config: AggregateConfig = serde_json::from_reader(reader)?;
+        for rule in &config.aggregates {
+            if rule.name.is_none() && rule.category.is_none() {
+                return Err(format!(
+                    "Aggregate {} matches neither a name nor a category",
+                    rule.aggregate
+                )
+                .into());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Hash of the rules, which changes when they map events differently
+    pub fn fingerprint(&self) -> String {
+        bytes_hash(format!("{:?}", self.aggregates).as_bytes())
+    }
+
+    /// The rules the event matches, with their index
+    pub fn matching<'a>(
+        &'a self,
+        trace_event: &'a TraceEvent,
+    ) -> impl Iterator<Item = (usize, &'a AggregateRule)> + 'a {
+        self.aggregates
+            .iter()
+            .enumerate()
+            .filter(|(_, rule)| rule.matches(trace_event))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::trace_event::TraceEvent;
+
+    use super::{AggregateConfig, KeyField};
+
+    #[test]
+    fn test_aggregate_rules() {
+        let config: AggregateConfig = serde_json::from_str(
+            r#"{"aggregates": [
+                {"aggregate": "pending", "name": "PerformPendingInstantiations"},
+                {"aggregate": "by_detail", "cat": "frontend", "key": "args.detail"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(config.aggregates[0].key, KeyField::Name);
+
+        let event: TraceEvent = serde_json::from_str(
+            r#"{"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 5, "cat": "frontend",
+                "name": "PerformPendingInstantiations", "args": {"detail": "x.cpp"}}"#,
+        )
+        .unwrap();
+        let keys: Vec<(usize, String)> = config
+            .matching(&event)
+            .map(|(index, rule)| (index, rule.key(&event).unwrap().into_owned()))
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                (0, "PerformPendingInstantiations".to_string()),
+                (1, "x.cpp".to_string())
+            ]
+        );
+
+        assert!(serde_json::from_str::<AggregateConfig>(
+            r#"{"aggregates": [{"aggregate": "a", "name": "b", "key": "detail"}]}"#
+        )
+        .is_err());
+    }
+}
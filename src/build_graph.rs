@@ -0,0 +1,648 @@
+/// The build graph of ninja, to find what bounds the wall time of the build
+extern crate log;
+
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap, HashSet},
+    error::Error,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use diesel::{prelude::*, SqliteConnection};
+use log::debug;
+
+use crate::{
+    compile_commands::normalize,
+    schema::{links, ninja_steps, objects},
+};
+
+/// A build edge: the command producing `outputs` from `inputs`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Step {
+    pub outputs: Vec<String>,
+    /// Explicit, implicit and order-only inputs alike
+    pub inputs: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct BuildGraph {
+    pub steps: Vec<Step>,
+}
+
+/// Steps which depend on themselves through their dependencies, by index
+#[derive(Debug, PartialEq, Eq)]
+pub struct Cycle(pub Vec<usize>);
+
+impl fmt::Display for Cycle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The build graph has a cycle through the steps {:?}",
+            self.0
+        )
+    }
+}
+
+impl Error for Cycle {}
+
+/// A piece of a `build` line
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Path(String),
+    Colon,
+    /// `|`, `||` or `|@`
+    Separator,
+}
+
+/// Expand `$name` and `${name}` with the variables, and the `$` escapes
+fn expand(text: &str, variables: &HashMap<String, String>) -> String {
+    let mut expanded = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        match chars.peek().copied() {
+            Some('{') => {
+                chars.next();
+                let name: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                expanded.push_str(variables.get(&name).map_or("", String::as_str));
+            }
+            Some(c) if c.is_ascii_alphanumeric() || c == '_' || c == '-' => {
+                let mut name = String::new();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                {
+                    name.push(c);
+                }
+                expanded.push_str(variables.get(&name).map_or("", String::as_str));
+            }
+            Some(c) => {
+                chars.next();
+                expanded.push(c);
+            }
+            None => {}
+        }
+    }
+    expanded
+}
+
+/// Split a `build` line into paths and separators, honoring the `$` escapes
+fn tokenize(text: &str, variables: &HashMap<String, String>) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars().peekable();
+    let flush = |current: &mut String, tokens: &mut Vec<Token>| {
+        if !current.is_empty() {
+            tokens.push(Token::Path(expand(current, variables)));
+            current.clear();
+        }
+    };
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => {
+                current.push(c);
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            ':' => {
+                flush(&mut current, &mut tokens);
+                tokens.push(Token::Colon);
+            }
+            '|' if current.is_empty() => {
+                chars.next_if(|c| *c == '|' || *c == '@');
+                tokens.push(Token::Separator);
+            }
+            c if c.is_whitespace() => flush(&mut current, &mut tokens),
+            c => current.push(c),
+        }
+    }
+    flush(&mut current, &mut tokens);
+    tokens
+}
+
+/// The lines of a manifest with the `$` continuations joined
+fn manifest_lines(content: &str) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    for line in content.lines() {
+        let line = if current.is_empty() {
+            line
+        } else {
+            line.trim_start()
+        };
+        let dollars = line.len() - line.trim_end_matches('$').len();
+        if dollars % 2 == 1 {
+            current.push_str(&line[..line.len() - 1]);
+        } else {
+            current.push_str(line);
+            lines.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
+
+impl BuildGraph {
+    /// Read `build.ninja` or the output of `ninja -t graph`, told apart by the content
+    pub fn load(path: &Path) -> Result<BuildGraph, Box<dyn Error + 'static>> {
+        let content = fs::read_to_string(path)?;
+        let mut graph = BuildGraph::default();
+        if content.trim_start().starts_with("digraph") {
+            graph.parse_dot(&content);
+        } else {
+            let directory = path.parent().unwrap_or(Path::new(""));
+            let mut parsing = HashSet::from([fs::canonicalize(path)?]);
+            graph.parse_manifest(&content, directory, &mut HashMap::new(), &mut parsing)?;
+        }
+        debug!(
+            "Build graph {} has {} steps",
+            path.display(),
+            graph.steps.len()
+        );
+        Ok(graph)
+    }
+
+    /// Add the `build` statements of a manifest, `directory` being where ninja runs and
+    /// `parsing` the manifests including it
+    fn parse_manifest(
+        &mut self,
+        content: &str,
+        directory: &Path,
+        variables: &mut HashMap<String, String>,
+        parsing: &mut HashSet<PathBuf>,
+    ) -> Result<(), Box<dyn Error + 'static>> {
+        for line in manifest_lines(content) {
+            if line.starts_with(char::is_whitespace) || line.starts_with('#') {
+                continue;
+            }
+            if let Some(rest) = line.strip_prefix("build ") {
+                let mut step = Step::default();
+                let mut in_outputs = true;
+                let mut rule_seen = false;
+                for token in tokenize(rest, variables) {
+                    match token {
+                        Token::Colon => in_outputs = false,
+                        Token::Separator => {}
+                        Token::Path(path) if in_outputs => step.outputs.push(path),
+                        // The first word after the colon is the rule
+                        Token::Path(_) if !rule_seen => rule_seen = true,
+                        Token::Path(path) => step.inputs.push(path),
+                    }
+                }
+                if step.outputs.is_empty() {
+                    return Err(format!("build statement without output: {}", line).into());
+                }
+                self.steps.push(step);
+            } else if let Some(include) = line
+                .strip_prefix("include ")
+                .or_else(|| line.strip_prefix("subninja "))
+            {
+                let included = directory.join(expand(include.trim(), variables));
+                let canonical = fs::canonicalize(&included)?;
+                if !parsing.insert(canonical.clone()) {
+                    return Err(format!("{} includes itself", included.display()).into());
+                }
+                let content = fs::read_to_string(&included)?;
+                // subninja has a scope of its own, but its paths are still relative to the
+                // directory ninja runs in
+                if line.starts_with("subninja ") {
+                    self.parse_manifest(&content, directory, &mut variables.clone(), parsing)?;
+                } else {
+                    self.parse_manifest(&content, directory, variables, parsing)?;
+                }
+                parsing.remove(&canonical);
+            } else if let Some((name, value)) = line.split_once('=') {
+                let name = name.trim();
+                if !name.is_empty() && !name.contains(char::is_whitespace) {
+                    let value = expand(value.trim_start(), variables);
+                    variables.insert(name.to_string(), value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add the steps of the `ninja -t graph` output, an edge is an ellipse or a labeled arrow
+    fn parse_dot(&mut self, content: &str) {
+        let attribute = |line: &str, name: &str| -> Option<String> {
+            let start = line.find(&format!("{}=\"", name))? + name.len() + 2;
+            let end = start + line[start..].find('"')?;
+            Some(line[start..end].to_string())
+        };
+        let node_id = |text: &str| text.trim().trim_matches('"').to_string();
+
+        let mut labels = HashMap::<String, String>::new();
+        let mut edges = HashMap::<String, usize>::new();
+        let mut arrows = Vec::<(String, String, bool)>::new();
+        for line in content.lines() {
+            let line = line.trim();
+            let (statement, attributes) = match line.find('[') {
+                Some(index) => (&line[..index], &line[index..]),
+                None => (line, ""),
+            };
+            if let Some((from, to)) = statement.split_once("->") {
+                // An arrow with a label is an edge of its own
+                let labeled = attribute(attributes, "label").is_some();
+                arrows.push((node_id(from), node_id(to), labeled));
+            } else if statement.starts_with('"') {
+                let id = node_id(statement);
+                if attributes.contains("shape=ellipse") {
+                    edges.insert(id, self.steps.len());
+                    self.steps.push(Step::default());
+                } else if let Some(label) = attribute(attributes, "label") {
+                    labels.insert(id, label);
+                }
+            }
+        }
+
+        let label = |id: &str| labels.get(id).cloned().unwrap_or_else(|| id.to_string());
+        for (from, to, labeled) in arrows {
+            if let Some(&edge) = edges.get(&to) {
+                self.steps[edge].inputs.push(label(&from));
+            } else if let Some(&edge) = edges.get(&from) {
+                self.steps[edge].outputs.push(label(&to));
+            } else if labeled {
+                self.steps.push(Step {
+                    outputs: vec![label(&to)],
+                    inputs: vec![label(&from)],
+                });
+            }
+        }
+    }
+
+    /// Index of the steps producing the inputs of every step, never the step itself
+    pub fn dependencies(&self) -> Vec<Vec<usize>> {
+        let producers: HashMap<&str, usize> = self
+            .steps
+            .iter()
+            .enumerate()
+            .flat_map(|(index, step)| {
+                step.outputs
+                    .iter()
+                    .map(move |output| (output.as_str(), index))
+            })
+            .collect();
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(index, step)| {
+                let mut dependencies: Vec<usize> = step
+                    .inputs
+                    .iter()
+                    .filter_map(|input| producers.get(input.as_str()).copied())
+                    .filter(|dependency| *dependency != index)
+                    .collect();
+                dependencies.sort_unstable();
+                dependencies.dedup();
+                dependencies
+            })
+            .collect()
+    }
+
+    /// The error of a cycle, naming the first output of its steps
+    pub fn cycle_error(&self, cycle: &Cycle) -> Box<dyn Error + 'static> {
+        let outputs: Vec<&str> = cycle
+            .0
+            .iter()
+            .filter_map(|step| self.steps[*step].outputs.first())
+            .map(String::as_str)
+            .collect();
+        format!(
+            "The build graph has a cycle through {}",
+            outputs.join(" -> ")
+        )
+        .into()
+    }
+
+    /// Time of every step in microseconds, from `ninja_steps` or else from its trace
+    pub fn durations(
+        &self,
+        build_directory: &Path,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<i64>, Box<dyn Error + 'static>> {
+        let measured: HashMap<String, i64> = ninja_steps::table
+            .select((
+                ninja_steps::output,
+                ninja_steps::start_ms,
+                ninja_steps::end_ms,
+            ))
+            .load::<(String, i64, i64)>(conn)?
+            .into_iter()
+            .map(|(output, start, end)| (output, (end - start) * 1000))
+            .collect();
+        let compiled: HashMap<_, i64> = objects::table
+            .select((objects::path, objects::total_time))
+            .load::<(String, i32)>(conn)?
+            .into_iter()
+            .map(|(path, total_time)| (normalize(Path::new(&path)), total_time as i64))
+            .collect();
+        // Links keep the extension of their output
+        let linked: HashMap<_, i64> = links::table
+            .select((links::path, links::total_time))
+            .load::<(String, i32)>(conn)?
+            .into_iter()
+            .map(|(path, total_time)| (normalize(Path::new(&path)), total_time as i64))
+            .collect();
+
+        Ok(self
+            .steps
+            .iter()
+            .map(|step| {
+                let outputs = step.outputs.iter();
+                outputs
+                    .clone()
+                    .filter_map(|output| measured.get(output).copied())
+                    .max()
+                    .or_else(|| {
+                        outputs
+                            .filter_map(|output| {
+                                let output = normalize(&build_directory.join(output));
+                                linked
+                                    .get(&output)
+                                    .or_else(|| compiled.get(&output.with_extension("")))
+                                    .copied()
+                            })
+                            .max()
+                    })
+                    .unwrap_or(0)
+            })
+            .collect())
+    }
+}
+
+/// Where the depth first search of [`finish_times`] is with a step
+#[derive(Clone, Copy)]
+enum Mark {
+    Unvisited,
+    /// Its dependencies are being visited, finding it again means a cycle
+    Visiting,
+    Finished(i64),
+}
+
+/// For every step, the time of the longest chain of steps ending with it
+fn finish_times(durations: &[i64], dependencies: &[Vec<usize>]) -> Result<Vec<i64>, Cycle> {
+    // Depth first, the finish time of a step is known once those of its dependencies are
+    let mut marks = vec![Mark::Unvisited; durations.len()];
+    for root in 0..durations.len() {
+        let mut stack = vec![(root, false)];
+        while let Some((step, expanded)) = stack.pop() {
+            match (marks[step], expanded) {
+                (Mark::Finished(_), _) => continue,
+                (Mark::Visiting, true) => {
+                    let before = dependencies[step]
+                        .iter()
+                        .filter_map(|dependency| match marks[*dependency] {
+                            Mark::Finished(finish) => Some(finish),
+                            _ => None,
+                        })
+                        .max()
+                        .unwrap_or(0);
+                    marks[step] = Mark::Finished(before + durations[step]);
+                    continue;
+                }
+                _ => {}
+            }
+            marks[step] = Mark::Visiting;
+            stack.push((step, true));
+            for dependency in &dependencies[step] {
+                match marks[*dependency] {
+                    Mark::Unvisited => stack.push((*dependency, false)),
+                    // A back edge, the steps being expanded from the dependency on are the cycle
+                    Mark::Visiting => {
+                        let expanding: Vec<usize> = stack
+                            .iter()
+                            .filter(|(_, expanded)| *expanded)
+                            .map(|(step, _)| *step)
+                            .collect();
+                        let start = expanding
+                            .iter()
+                            .position(|step| step == dependency)
+                            .unwrap_or(0);
+                        return Err(Cycle(expanding[start..].to_vec()));
+                    }
+                    Mark::Finished(_) => {}
+                }
+            }
+        }
+    }
+    Ok(marks
+        .into_iter()
+        .map(|mark| match mark {
+            Mark::Finished(finish) => finish,
+            _ => 0,
+        })
+        .collect())
+}
+
+/// The chain of steps the build cannot be faster than, and its duration
+pub fn critical_path(
+    durations: &[i64],
+    dependencies: &[Vec<usize>],
+) -> Result<(i64, Vec<usize>), Cycle> {
+    let finish = finish_times(durations, dependencies)?;
+    let Some(mut step) = (0..finish.len()).max_by_key(|step| (finish[*step], Reverse(*step)))
+    else {
+        return Ok((0, Vec::new()));
+    };
+    let length = finish[step];
+    let mut path = vec![step];
+    while let Some(&previous) = dependencies[step]
+        .iter()
+        .max_by_key(|dependency| (finish[**dependency], Reverse(**dependency)))
+    {
+        step = previous;
+        path.push(step);
+    }
+    path.reverse();
+    Ok((length, path))
+}
+
+/// Wall time of the build with `jobs` steps run at the same time, scheduling the ready step with
+/// the longest chain depending on it first as ninja does
+pub fn simulate(durations: &[i64], dependencies: &[Vec<usize>], jobs: usize) -> Result<i64, Cycle> {
+    let count = durations.len();
+    let mut dependents = vec![Vec::new(); count];
+    let mut waiting: Vec<usize> = dependencies.iter().map(Vec::len).collect();
+    for (step, step_dependencies) in dependencies.iter().enumerate() {
+        for dependency in step_dependencies {
+            dependents[*dependency].push(step);
+        }
+    }
+    // The longest chain starting with each step, through its dependents
+    let reversed: Vec<i64> = finish_times(durations, &dependents)?;
+
+    let mut ready: BinaryHeap<(i64, Reverse<usize>)> = (0..count)
+        .filter(|step| waiting[*step] == 0)
+        .map(|step| (reversed[step], Reverse(step)))
+        .collect();
+    let mut running = BinaryHeap::<Reverse<(i64, usize)>>::new();
+    let mut now = 0;
+    loop {
+        while running.len() < jobs.max(1) {
+            let Some((_, Reverse(step))) = ready.pop() else {
+                break;
+            };
+            running.push(Reverse((now + durations[step], step)));
+        }
+        let Some(Reverse((finish, step))) = running.pop() else {
+            break;
+        };
+        now = finish;
+        for dependent in &dependents[step] {
+            waiting[*dependent] -= 1;
+            if waiting[*dependent] == 0 {
+                ready.push((reversed[*dependent], Reverse(*dependent)));
+            }
+        }
+    }
+    let never_started: Vec<usize> = (0..count).filter(|step| waiting[*step] > 0).collect();
+    if !never_started.is_empty() {
+        return Err(Cycle(never_started));
+    }
+    Ok(now)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+
+    use crate::test_dir::TestDir;
+
+    use super::{
+        critical_path, manifest_lines, simulate, tokenize, BuildGraph, Cycle, Step, Token,
+    };
+
+    #[test]
+    fn test_parse_build_graph() {
+        let variables = HashMap::from([("out".to_string(), "obj".to_string())]);
+        assert_eq!(
+            tokenize("$out/a$ b.o | $out/a.d: cxx ../a.cpp || gen", &variables),
+            vec![
+                Token::Path("obj/a b.o".to_string()),
+                Token::Separator,
+                Token::Path("obj/a.d".to_string()),
+                Token::Colon,
+                Token::Path("cxx".to_string()),
+                Token::Path("../a.cpp".to_string()),
+                Token::Separator,
+                Token::Path("gen".to_string()),
+            ]
+        );
+        assert_eq!(
+            manifest_lines("build a: cc $\n    b.c\nx = $$"),
+            vec!["build a: cc b.c", "x = $$"]
+        );
+
+        let mut graph = BuildGraph::default();
+        graph
+            .parse_manifest(
+                "builddir = obj\nrule cc\n  command = cc $in -o $out\n\
+                build ${builddir}/a.o: cc a.c | gen.h\nbuild app: link obj/a.o obj/b.o\n",
+                std::path::Path::new("."),
+                &mut HashMap::new(),
+                &mut HashSet::new(),
+            )
+            .unwrap();
+        assert_eq!(
+            graph.steps[0],
+            Step {
+                outputs: vec!["obj/a.o".to_string()],
+                inputs: vec!["a.c".to_string(), "gen.h".to_string()],
+            }
+        );
+        assert_eq!(graph.dependencies(), vec![vec![], vec![0]]);
+
+        let mut dot = BuildGraph::default();
+        dot.parse_dot(
+            r#"digraph ninja {
+rankdir="LR"
+node [fontsize=10, shape=box, height=0.25]
+"0x1" [label="app"]
+"0x2" [label="link", shape=ellipse]
+"0x2" -> "0x1"
+"0x3" -> "0x2" [arrowhead=none]
+"0x3" [label="obj/a.o"]
+"0x4" -> "0x2" [arrowhead=none]
+"0x4" [label="obj/b.o"]
+"0x5" -> "0x3" [label=" cc"]
+"0x5" [label="a.c"]
+}"#,
+        );
+        assert_eq!(dot.steps.len(), 2);
+        assert_eq!(dot.steps[0].outputs, vec!["app"]);
+        assert_eq!(dot.steps[0].inputs, vec!["obj/a.o", "obj/b.o"]);
+        assert_eq!(dot.steps[1].outputs, vec!["obj/a.o"]);
+    }
+
+    #[test]
+    fn test_manifest_includes() {
+        let directory = TestDir::new("manifest-includes");
+        directory.write("rules.ninja", "rule cc\n  command = cc $in -o $out\n");
+        directory.write("a.ninja", "include rules.ninja\nbuild a.o: cc a.c\n");
+        let manifest = directory.write(
+            "build.ninja",
+            "include rules.ninja\nsubninja a.ninja\nsubninja a.ninja\n",
+        );
+        // The same manifest may be included twice, as long as it does not include itself
+        assert_eq!(BuildGraph::load(&manifest).unwrap().steps.len(), 2);
+
+        directory.write("b.ninja", "include c.ninja\n");
+        directory.write("c.ninja", "build c.o: cc c.c\nsubninja b.ninja\n");
+        let cycle = directory.write("cycle.ninja", "include b.ninja\n");
+        let error = BuildGraph::load(&cycle).unwrap_err();
+        assert!(error.to_string().ends_with("b.ninja includes itself"));
+        let missing = directory.write("missing.ninja", "include nowhere.ninja\n");
+        assert!(BuildGraph::load(&missing).is_err());
+    }
+
+    #[test]
+    fn test_critical_path_and_simulation() {
+        // 0 and 1 compile, 2 is a generator 1 waits for, 3 links 0 and 1
+        let durations = [50, 30, 40, 10];
+        let dependencies = vec![vec![], vec![2], vec![], vec![0, 1]];
+        assert_eq!(
+            critical_path(&durations, &dependencies),
+            Ok((80, vec![2, 1, 3]))
+        );
+        assert_eq!(simulate(&durations, &dependencies, 1), Ok(130));
+        // The generator starts first, the longest chain
+        assert_eq!(simulate(&durations, &dependencies, 2), Ok(80));
+        assert_eq!(simulate(&durations, &dependencies, 8), Ok(80));
+    }
+
+    #[test]
+    fn test_cycles() {
+        // A phony target naming itself is not a cycle
+        let mut graph = BuildGraph::default();
+        graph
+            .parse_manifest(
+                "rule cc\n  command = cc $in -o $out\nbuild a.o: cc a.c\n\
+                build all: phony all a.o\n",
+                std::path::Path::new("."),
+                &mut HashMap::new(),
+                &mut HashSet::new(),
+            )
+            .unwrap();
+        let dependencies = graph.dependencies();
+        assert_eq!(dependencies, vec![vec![], vec![0]]);
+        assert_eq!(critical_path(&[10, 0], &dependencies), Ok((10, vec![0])));
+
+        // 1 and 2 depend on each other, 3 waits for 2
+        let durations = [10, 20, 30, 40];
+        let dependencies = vec![vec![], vec![0, 2], vec![1], vec![2]];
+        assert_eq!(
+            critical_path(&durations, &dependencies),
+            Err(Cycle(vec![1, 2]))
+        );
+        assert_eq!(
+            simulate(&durations, &dependencies, 2),
+            Err(Cycle(vec![1, 2]))
+        );
+    }
+}
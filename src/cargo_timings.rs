@@ -0,0 +1,258 @@
+/// Import the units of `cargo build --timings`, from the HTML report or `--timings=json`
+extern crate log;
+extern crate serde;
+extern crate serde_json;
+
+use std::{collections::HashMap, error::Error, fs, path::Path};
+
+use diesel::{prelude::*, SqliteConnection};
+use log::debug;
+use serde::Deserialize;
+
+use crate::{file_parser::INSERT_BATCH_SIZE, schema::cargo_units, tracedb::NewCargoUnit};
+
+/// A unit of the build, times in seconds as cargo gives them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unit {
+    pub name: String,
+    pub version: String,
+    /// The target of the crate, empty for the library, e.g. `build script` or `bin "tool"`
+    pub target: String,
+    /// `build` or `run-custom-build` for the run of a build script, ...
+    pub mode: String,
+    /// Since cargo started, only known from the HTML report
+    pub start: Option<f64>,
+    pub duration: f64,
+    /// When the metadata was ready, relative to the start of the unit
+    pub rmeta_time: Option<f64>,
+}
+
+/// A unit of `UNIT_DATA`
+#[derive(Deserialize)]
+struct HtmlUnit {
+    name: String,
+    version: String,
+    target: String,
+    mode: String,
+    start: f64,
+    duration: f64,
+    rmeta_time: Option<f64>,
+}
+
+/// A message printed by `--timings=json`
+#[derive(Deserialize)]
+struct TimingInfo {
+    reason: String,
+    package_id: String,
+    target: Option<TimingTarget>,
+    mode: Option<String>,
+    duration: Option<f64>,
+    rmeta_time: Option<f64>,
+}
+
+#[derive(Deserialize)]
+struct TimingTarget {
+    name: String,
+    kind: Vec<String>,
+}
+
+/// Name and version of a package id, `name version (source)` or `source#name@version`
+fn package_name_version(package_id: &str) -> (String, String) {
+    if let Some((source, fragment)) = package_id.rsplit_once('#') {
+        return match fragment.split_once('@') {
+            Some((name, version)) => (name.to_string(), version.to_string()),
+            None => {
+                let name = source.trim_end_matches('/').rsplit('/').next();
+                (name.unwrap_or_default().to_string(), fragment.to_string())
+            }
+        };
+    }
+    let mut fields = package_id.split_whitespace();
+    let name = fields.next().unwrap_or_default().to_string();
+    let version = fields.next().unwrap_or_default().to_string();
+    (name, version)
+}
+
+/// The target the way the HTML report writes it, empty for the library
+fn target_description(target: &TimingTarget) -> String {
+    match target.kind.first().map(String::as_str) {
+        None | Some("lib" | "rlib" | "dylib" | "proc-macro") => String::new(),
+        Some("custom-build") => "build script".to_string(),
+        Some(kind) => format!("{} \"{}\"", kind, target.name),
+    }
+}
+
+/// Read the units of the HTML report or of the `--timings=json` messages
+pub fn parse(content: &str) -> Result<Vec<Unit>, Box<dyn Error + 'static>> {
+    if let Some(position) = content.find("UNIT_DATA") {
+        let data = content[position..]
+            .split_once('=')
+            .map(|(_, data)| data)
+            .ok_or("UNIT_DATA has no value")?;
+        let units = serde_json::Deserializer::from_str(data)
+            .into_iter::<Vec<HtmlUnit>>()
+            .next()
+            .ok_or("UNIT_DATA has no value")??;
+        return Ok(keep_last(units.into_iter().map(|unit| Unit {
+            name: unit.name,
+            version: unit.version,
+            target: unit.target.trim().to_string(),
+            mode: unit.mode,
+            start: Some(unit.start),
+            duration: unit.duration,
+            rmeta_time: unit.rmeta_time,
+        })));
+    }
+
+    let mut units = Vec::new();
+    for line in content.lines() {
+        // Cargo prints the other messages and the compiler diagnostics on the same stream
+        let Ok(message) = serde_json::from_str::<TimingInfo>(line) else {
+            continue;
+        };
+        let (Some(target), Some(mode), Some(duration)) =
+            (&message.target, message.mode, message.duration)
+        else {
+            continue;
+        };
+        if message.reason != "timing-info" {
+            continue;
+        }
+        let (name, version) = package_name_version(&message.package_id);
+        units.push(Unit {
+            name,
+            version,
+            target: target_description(target),
+            mode,
+            start: None,
+            duration,
+            rmeta_time: message.rmeta_time,
+        });
+    }
+    Ok(keep_last(units))
+}
+
+/// The last timing of every unit, in the order the units first appear
+fn keep_last(units: impl IntoIterator<Item = Unit>) -> Vec<Unit> {
+    let mut kept = Vec::<Unit>::new();
+    let mut by_key = HashMap::<(String, String, String, String), usize>::new();
+    for unit in units {
+        let key = (
+            unit.name.clone(),
+            unit.version.clone(),
+            unit.target.clone(),
+            unit.mode.clone(),
+        );
+        match by_key.get(&key) {
+            Some(&index) => kept[index] = unit,
+            None => {
+                by_key.insert(key, kept.len());
+                kept.push(unit);
+            }
+        }
+    }
+    kept
+}
+
+pub fn load(path: &Path) -> Result<Vec<Unit>, Box<dyn Error + 'static>> {
+    parse(&fs::read_to_string(path)?)
+}
+
+/// Seconds to microseconds, the unit of the times in the database
+fn microseconds(seconds: f64) -> i64 {
+    (seconds * 1_000_000.0).round() as i64
+}
+
+/// Replace the content of `cargo_units` with the units, as [`parse`] returns them, one per key
+pub fn import_cargo_timings(
+    units: &[Unit],
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error + 'static>> {
+    let rows: Vec<NewCargoUnit> = units
+        .iter()
+        .map(|unit| NewCargoUnit {
+            name: &unit.name,
+            version: &unit.version,
+            target: &unit.target,
+            mode: &unit.mode,
+            start_time: unit.start.map(microseconds),
+            duration: microseconds(unit.duration),
+            rmeta_time: unit.rmeta_time.map(microseconds),
+        })
+        .collect();
+    debug!("Import {} cargo units", rows.len());
+
+    conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+        diesel::delete(cargo_units::table).execute(conn)?;
+        for batch in rows.chunks(INSERT_BATCH_SIZE) {
+            diesel::insert_into(cargo_units::table)
+                .values(batch)
+                .execute(conn)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use diesel::prelude::*;
+
+    use crate::{schema::cargo_units, tracedb::get_connection};
+
+    use super::{import_cargo_timings, parse};
+
+    #[test]
+    fn test_cargo_timings() {
+        let html = r#"<script>
+const UNIT_DATA = [
+  {"i": 0, "name": "serde", "version": "1.0.190", "mode": "build", "target": "", "start": 0.25,
+   "duration": 3.5, "rmeta_time": 1.25, "unlocked_units": [], "unlocked_rmeta_units": [1]},
+  {"i": 1, "name": "app", "version": "0.1.0", "mode": "run-custom-build",
+   "target": " build script", "start": 1.5, "duration": 0.5, "rmeta_time": null,
+   "unlocked_units": [], "unlocked_rmeta_units": []}
+];
+const CONCURRENCY_DATA = [];
+</script>"#;
+        let units = parse(html).unwrap();
+        assert_eq!(units.len(), 2);
+        assert_eq!(units[1].target, "build script");
+        assert_eq!(units[0].start, Some(0.25));
+
+        let json = r#"{"reason":"compiler-artifact","package_id":"serde 1.0.190"}
+{"reason":"timing-info","package_id":"registry+https://github.com/rust-lang/crates.io-index#serde@1.0.190","target":{"kind":["lib"],"name":"serde"},"mode":"build","duration":3.5,"rmeta_time":1.25}
+{"reason":"timing-info","package_id":"path+file:///src/app#0.1.0","target":{"kind":["bin"],"name":"tool"},"mode":"build","duration":2.0,"rmeta_time":0.5}
+{"reason":"build-finished","success":true}"#;
+        let units = parse(json).unwrap();
+        assert_eq!(
+            units
+                .iter()
+                .map(|unit| (
+                    unit.name.as_str(),
+                    unit.version.as_str(),
+                    unit.target.as_str()
+                ))
+                .collect::<Vec<_>>(),
+            vec![("serde", "1.0.190", ""), ("app", "0.1.0", "bin \"tool\"")]
+        );
+
+        let mut conn = get_connection(":memory:").unwrap();
+        import_cargo_timings(&units, &mut conn).unwrap();
+        import_cargo_timings(&units, &mut conn).unwrap();
+        let rows: Vec<(String, i64, Option<i64>)> = cargo_units::table
+            .select((
+                cargo_units::name,
+                cargo_units::duration,
+                cargo_units::rmeta_time,
+            ))
+            .order(cargo_units::name)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("app".to_string(), 2000000, Some(500000)),
+                ("serde".to_string(), 3500000, Some(1250000)),
+            ]
+        );
+    }
+}
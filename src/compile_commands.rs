@@ -0,0 +1,257 @@
+/// Link the objects to the entries of `compile_commands.json` by their output path
+extern crate log;
+extern crate serde;
+extern crate serde_json;
+
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs::File,
+    io::BufReader,
+    path::{Component, Path, PathBuf},
+};
+
+use diesel::{prelude::*, SqliteConnection};
+use log::debug;
+use serde::Deserialize;
+
+use crate::{
+    file_parser::INSERT_BATCH_SIZE,
+    schema::{compile_commands, objects},
+    tracedb::NewCompileCommand,
+};
+
+/// One entry of the compilation database
+#[derive(Debug, Clone, Deserialize)]
+pub struct Entry {
+    pub directory: String,
+    pub file: String,
+    /// The command as a single string, if `arguments` is not given
+    pub command: Option<String>,
+    pub arguments: Option<Vec<String>>,
+    pub output: Option<String>,
+}
+
+/// Split a command on whitespace, honoring quotes and backslash escapes like a shell would
+pub fn split_command(command: &str) -> Vec<String> {
+    let mut arguments = Vec::new();
+    let mut current: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                current.get_or_insert_with(String::new);
+            }
+            (q, '\\') if q != Some('\'') => {
+                if let Some(escaped) = chars.next() {
+                    current.get_or_insert_with(String::new).push(escaped);
+                }
+            }
+            (None, c) if c.is_whitespace() => arguments.extend(current.take()),
+            (_, c) => current.get_or_insert_with(String::new).push(c),
+        }
+    }
+    arguments.extend(current);
+    arguments
+}
+
+impl Entry {
+    /// The arguments of the command, `command` is split with [`split_command`]
+    fn arguments(&self) -> Vec<String> {
+        match &self.arguments {
+            Some(arguments) => arguments.clone(),
+            None => split_command(self.command.as_deref().unwrap_or_default()),
+        }
+    }
+
+    /// The full command, `arguments` joined with quotes where needed
+    pub fn command(&self) -> String {
+        match &self.command {
+            Some(command) => command.clone(),
+            None => self
+                .arguments()
+                .iter()
+                .map(|argument| {
+                    if argument.is_empty() || argument.contains(char::is_whitespace) {
+                        format!("'{}'", argument.replace('\'', r"'\''"))
+                    } else {
+                        argument.clone()
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+
+    /// Where the object is written, by default the source file name with the `.o` extension
+    pub fn output(&self) -> PathBuf {
+        let output = self.output.clone().or_else(|| {
+            let arguments = self.arguments();
+            let mut iter = arguments.iter();
+            while let Some(argument) = iter.next() {
+                if argument == "-o" {
+                    return iter.next().cloned();
+                }
+                if let Some(output) = argument.strip_prefix("-o") {
+                    return Some(output.to_string());
+                }
+            }
+            None
+        });
+        let output = output.map(PathBuf::from).unwrap_or_else(|| {
+            Path::new(&self.file)
+                .with_extension("o")
+                .file_name()
+                .map(PathBuf::from)
+                .unwrap_or_default()
+        });
+        normalize(&Path::new(&self.directory).join(output))
+    }
+
+    pub fn source(&self) -> PathBuf {
+        normalize(&Path::new(&self.directory).join(&self.file))
+    }
+}
+
+/// Make the path absolute and resolve `.` and `..` without touching the file system
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+pub fn load(path: &Path) -> Result<Vec<Entry>, Box<dyn Error + 'static>> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Replace the content of `compile_commands` with the entries matching an object, returns how
+/// many objects were linked
+pub fn link_compile_commands(
+    entries: &[Entry],
+    conn: &mut SqliteConnection,
+) -> Result<usize, Box<dyn Error + 'static>> {
+    let by_object: HashMap<PathBuf, &Entry> = entries
+        .iter()
+        .map(|entry| (entry.output().with_extension(""), entry))
+        .collect();
+
+    let objects: Vec<String> = objects::table.select(objects::path).load(conn)?;
+    let links: Vec<(String, String, String, String, String)> = objects
+        .into_iter()
+        .filter_map(|object| {
+            let entry = by_object.get(&normalize(Path::new(&object)))?;
+            Some((
+                entry.source().to_string_lossy().into_owned(),
+                entry.output().to_string_lossy().into_owned(),
+                entry.directory.clone(),
+                entry.command(),
+                object,
+            ))
+        })
+        .collect();
+    let rows: Vec<NewCompileCommand> = links
+        .iter()
+        .map(
+            |(source, output, directory, command, object)| NewCompileCommand {
+                object,
+                source,
+                output,
+                directory,
+                command,
+            },
+        )
+        .collect();
+    debug!("Link {} objects to compile commands", rows.len());
+
+    conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+        diesel::delete(compile_commands::table).execute(conn)?;
+        for batch in rows.chunks(INSERT_BATCH_SIZE) {
+            diesel::insert_into(compile_commands::table)
+                .values(batch)
+                .execute(conn)?;
+        }
+        Ok(())
+    })?;
+    Ok(rows.len())
+}
+
+#[cfg(test)]
+mod test {
+    use diesel::prelude::*;
+
+    use crate::{
+        schema::compile_commands,
+        tracedb::{get_connection, NewObject},
+    };
+
+    use super::{link_compile_commands, Entry};
+
+    #[test]
+    fn test_link_compile_commands() {
+        let entries: Vec<Entry> = serde_json::from_str(
+            r#"[
+                {"directory": "/build", "file": "../src/a.cpp",
+                 "command": "clang++ -I\"/opt/my include\" -c ../src/a.cpp -o obj/a.cpp.o"},
+                {"directory": "/build", "file": "/src/b.cpp",
+                 "arguments": ["clang++", "-DNAME=a b", "-c", "/src/b.cpp", "-oobj/b.o"]},
+                {"directory": "/build", "file": "/src/c.cpp", "arguments": ["clang++", "-c", "/src/c.cpp"]}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            entries[1].command(),
+            "clang++ '-DNAME=a b' -c /src/b.cpp -oobj/b.o"
+        );
+
+        let mut conn = get_connection(":memory:").unwrap();
+        for object in ["/build/obj/a.cpp", "/build/./obj/b", "/build/c", "/build/d"] {
+            NewObject::new(object, 0, 0, 0, false, None, None)
+                .insert(&mut conn)
+                .unwrap();
+        }
+        assert_eq!(link_compile_commands(&entries, &mut conn).unwrap(), 3);
+
+        let rows: Vec<(String, String, String)> = compile_commands::table
+            .select((
+                compile_commands::object,
+                compile_commands::source,
+                compile_commands::output,
+            ))
+            .order(compile_commands::object)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                (
+                    "/build/./obj/b".to_string(),
+                    "/src/b.cpp".to_string(),
+                    "/build/obj/b.o".to_string()
+                ),
+                (
+                    "/build/c".to_string(),
+                    "/src/c.cpp".to_string(),
+                    "/build/c.o".to_string()
+                ),
+                (
+                    "/build/obj/a.cpp".to_string(),
+                    "/src/a.cpp".to_string(),
+                    "/build/obj/a.cpp.o".to_string()
+                ),
+            ]
+        );
+    }
+}
@@ -1,19 +1,228 @@
 /// Walk through the directory
-extern crate glob;
+extern crate globset;
+extern crate ignore;
 extern crate log;
 
-use glob::{glob, Paths};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use ignore::{DirEntry, WalkBuilder};
 use log::debug;
-use std::{error::Error, path::Path};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
 
-pub fn iterate_json_files(base_directory: &Path) -> Result<Paths, Box<dyn Error>> {
-    let path = base_directory.join("**").join("*.json");
-    let path_str = path.to_str().expect("Failed to convert path to string");
+use crate::file_parser::COMPRESSION_EXTENSIONS;
 
-    debug!("Search pattern {}", path_str);
+/// Which files of the directories are considered, patterns with a `/` match the relative path
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    /// Only traces matching one of these are kept, all traces if empty
+    pub include: Vec<String>,
+    /// Files and directories matching one of these are skipped, directories are not descended into
+    pub exclude: Vec<String>,
+    /// Honor `.gitignore`, `.ignore` and the git excludes
+    pub ignore_files: bool,
+    /// Levels of directories to descend into below a root, unlimited if `None`
+    pub max_depth: Option<usize>,
+    pub follow_links: bool,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            include: Vec::new(),
+            exclude: Vec::new(),
+            ignore_files: false,
+            max_depth: None,
+            follow_links: true,
+        }
+    }
+}
+
+/// Patterns split by what they are matched against
+#[derive(Clone)]
+struct Patterns {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Patterns {
+    fn new<S: AsRef<str>>(patterns: &[S]) -> Result<Patterns, globset::Error> {
+        let mut names = GlobSetBuilder::new();
+        let mut paths = GlobSetBuilder::new();
+        for pattern in patterns {
+            let pattern = pattern.as_ref();
+            if pattern.contains('/') {
+                paths.add(Glob::new(pattern.trim_start_matches('/'))?);
+            } else {
+                names.add(Glob::new(pattern)?);
+            }
+        }
+        Ok(Patterns {
+            names: names.build()?,
+            paths: paths.build()?,
+        })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.paths.is_empty()
+    }
+
+    fn matches(&self, root: &Path, path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|name| self.names.is_match(name))
+            || path
+                .strip_prefix(root)
+                .is_ok_and(|relative| self.paths.is_match(relative))
+    }
+}
+
+/// The name of the traces, `*.json`, `*.time-trace` and `*.time-report`, compressed or not
+fn trace_patterns() -> Vec<String> {
+    let mut patterns = Vec::new();
+    for name in ["*.json", "*.time-trace", "*.time-report"] {
+        patterns.push(name.to_string());
+        for extension in COMPRESSION_EXTENSIONS {
+            patterns.push(format!("{}.{}", name, extension));
+        }
+    }
+    patterns
+}
+
+/// Find the traces under the root directories
+pub fn iterate_json_files(
+    roots: &[PathBuf],
+    options: &WalkOptions,
+) -> Result<impl Iterator<Item = Result<PathBuf, ignore::Error>>, Box<dyn Error>> {
+    let traces = Patterns::new(&trace_patterns())?;
+    let include = Patterns::new(&options.include)?;
+    let exclude = Patterns::new(&options.exclude)?;
+
+    let mut walks = Vec::new();
+    for root in roots {
+        debug!("Search traces under {}", root.display());
+
+        let mut builder = WalkBuilder::new(root);
+        builder
+            .standard_filters(false)
+            .git_ignore(options.ignore_files)
+            .git_global(options.ignore_files)
+            .git_exclude(options.ignore_files)
+            .ignore(options.ignore_files)
+            .parents(options.ignore_files)
+            .require_git(false)
+            .max_depth(options.max_depth.map(|depth| depth + 1))
+            .follow_links(options.follow_links);
+        if !exclude.is_empty() {
+            let exclude = exclude.clone();
+            let root = root.clone();
+            builder.filter_entry(move |entry| !exclude.matches(&root, entry.path()));
+        }
+        walks.push((root.clone(), builder.build()));
+    }
+
+    let is_trace = move |root: &Path, entry: &DirEntry| {
+        entry
+            .file_type()
+            .is_some_and(|file_type| !file_type.is_dir())
+            && traces.matches(root, entry.path())
+            && (include.is_empty() || include.matches(root, entry.path()))
+    };
+    Ok(walks.into_iter().flat_map(move |(root, walk)| {
+        let is_trace = is_trace.clone();
+        walk.filter_map(move |entry| match entry {
+            Ok(entry) if is_trace(&root, &entry) => Some(Ok(entry.into_path())),
+            Ok(_) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, path::PathBuf};
+
+    use super::{iterate_json_files, WalkOptions};
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_walk_options() {
+        let directory = TestDir::new("walker");
+        let root = directory.path().to_path_buf();
+        for subdirectory in ["src", "src/deep", "_deps/fmt", "node_modules"] {
+            fs::create_dir_all(root.join(subdirectory)).unwrap();
+        }
+        for file in [
+            "a.json",
+            "b.json.gz",
+            "app.time-trace",
+            "notes.txt",
+            "src/c.json",
+            "src/deep/d.json",
+            "_deps/fmt/e.json",
+            "node_modules/f.json",
+        ] {
+            directory.write(file, "{}");
+        }
+        directory.write(".ignore", "node_modules\n");
+
+        let walk = |options: &WalkOptions| {
+            let mut paths: Vec<PathBuf> = iterate_json_files(std::slice::from_ref(&root), options)
+                .unwrap()
+                .map(|path| path.unwrap().strip_prefix(&root).unwrap().to_path_buf())
+                .collect();
+            paths.sort();
+            paths
+        };
+        let expected =
+            |paths: &[&str]| -> Vec<PathBuf> { paths.iter().map(PathBuf::from).collect() };
+
+        assert_eq!(walk(&WalkOptions::default()).len(), 7);
+        assert_eq!(
+            walk(&WalkOptions {
+                exclude: vec!["_deps".to_string()],
+                ignore_files: true,
+                ..Default::default()
+            }),
+            expected(&[
+                "a.json",
+                "app.time-trace",
+                "b.json.gz",
+                "src/c.json",
+                "src/deep/d.json"
+            ])
+        );
+        assert_eq!(
+            walk(&WalkOptions {
+                include: vec!["src/**".to_string()],
+                max_depth: Some(1),
+                ..Default::default()
+            }),
+            expected(&["src/c.json"])
+        );
+    }
+
+    #[test]
+    fn test_walk_errors() {
+        let directory = TestDir::new("walker-errors");
+        let roots = [directory.path().to_path_buf()];
+        let options = WalkOptions {
+            exclude: vec!["src/[".to_string()],
+            ..Default::default()
+        };
+        assert!(iterate_json_files(&roots, &options).is_err());
 
-    match glob(path_str) {
-        Ok(result) => Ok(result),
-        Err(e) => Err(Box::new(e)),
+        // A missing root is reported by the walk, the other roots are still walked
+        directory.write("a.json", "{}");
+        let roots = [
+            directory.path().join("missing"),
+            directory.path().to_path_buf(),
+        ];
+        let walk: Vec<_> = iterate_json_files(&roots, &WalkOptions::default())
+            .unwrap()
+            .collect();
+        assert_eq!(walk.len(), 2);
+        assert!(walk[0].is_err());
+        assert_eq!(walk[1].as_ref().unwrap(), &directory.path().join("a.json"));
     }
 }
@@ -1,14 +1,32 @@
-use std::{error::Error, fs::File, io::BufReader, path::Path};
+use std::{
+    collections::{BTreeMap, HashMap},
+    error::Error,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use diesel::{prelude::*, upsert::excluded, SqliteConnection};
-use log::debug;
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
 
-use crate::schema::{instantiate_class, instantiate_function, parse_class, parse_template, source};
+use crate::schema::{
+    aggregates, backend_phase, codegen_function, gcc_passes, include_edges, instantiate_class,
+    instantiate_function, link_phases, links, object_aggregates, object_facts, objects,
+    opt_function, parse_class, parse_template, raw_event_objects, raw_events, source,
+    thinlto_modules, trace_metadata, trace_threads,
+};
 use crate::{
-    trace_event::{TraceEvent, TraceEvents},
+    aggregate_config::AggregateConfig,
+    time_report::{self, Pass},
+    trace_event::{is_truncated, stream_trace_events, EventType, TraceEvent, TraceHeader},
     tracedb::{
-        NewInstantiateClass, NewInstantiateFunction, NewObject, NewParseClass, NewParseTemplate,
-        NewSource,
+        NewAggregate, NewBackendPhase, NewCodeGenFunction, NewGccPass, NewIncludeEdge,
+        NewInstantiateClass, NewInstantiateFunction, NewLink, NewLinkPhase, NewObject,
+        NewObjectAggregate, NewObjectFact, NewOptFunction, NewParseClass, NewParseTemplate,
+        NewRawEvent, NewRawEventObject, NewSource, NewThinLtoModule, NewTraceMetadata,
+        NewTraceThread, Object, ObjectAggregate, ObjectFact,
     },
 };
 
@@ -24,124 +42,1869 @@ fn get_detail(trace_event: &TraceEvent) -> Option<&str> {
     None
 }
 
+/// Only called on complete events, which always have a duration
 #[inline]
 fn get_duration(trace_event: &TraceEvent) -> i32 {
-    trace_event.duration.unwrap().try_into().unwrap()
+    trace_event.duration.map_or(0, saturate)
+}
+
+/// Duration and occurrences of one key, summed over the trace
+#[derive(Default)]
+struct Aggregate {
+    /// Inclusive duration
+    duration: i32,
+    /// Duration minus the time spent in nested events of the same family
+    exclusive_duration: i32,
+    count: i32,
+}
+
+impl Aggregate {
+    fn add(&mut self, duration: u64, exclusive_duration: u64) {
+        self.duration = self.duration.saturating_add(saturate(duration));
+        self.exclusive_duration = self
+            .exclusive_duration
+            .saturating_add(saturate(exclusive_duration));
+        self.count = self.count.saturating_add(1);
+    }
+}
+
+/// Events only a linker emits, `lld --time-trace` wraps the whole link in `ExecuteLinker`
+const LINKER_EVENTS: [&str; 4] = [
+    "ExecuteLinker",
+    "Parse input files",
+    "Write output file",
+    "LTO",
+];
+
+/// Events only a compiler frontend emits, the trace is then not the one of a link
+const FRONTEND_EVENTS: [&str; 6] = [
+    "Frontend",
+    "Source",
+    "ParseClass",
+    "ParseTemplate",
+    "InstantiateClass",
+    "InstantiateFunction",
+];
+
+/// Names of the processes of the linkers writing time traces
+const LINKER_PROCESSES: [&str; 5] = ["ld.lld", "ld64.lld", "lld-link", "wasm-ld", "lld"];
+
+/// Events of the ThinLTO backend, one per module with the module as detail
+const THINLTO_BACKEND_EVENTS: [&str; 2] = ["Thin backend", "ThinLTO backend"];
+
+/// Events aggregated per `args.detail` into a global table of their own
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Builtin {
+    Source,
+    InstantiateClass,
+    InstantiateFunction,
+    ParseClass,
+    ParseTemplate,
+    CodeGenFunction,
+    OptFunction,
+    /// Module wide backend events, keyed by the event name
+    BackendPhase,
+}
+
+impl Builtin {
+    const ALL: [Builtin; 8] = [
+        Builtin::Source,
+        Builtin::InstantiateClass,
+        Builtin::InstantiateFunction,
+        Builtin::ParseClass,
+        Builtin::ParseTemplate,
+        Builtin::CodeGenFunction,
+        Builtin::OptFunction,
+        Builtin::BackendPhase,
+    ];
+
+    fn from_name(name: &str) -> Option<Builtin> {
+        match name {
+            "Source" => Some(Builtin::Source),
+            "InstantiateClass" => Some(Builtin::InstantiateClass),
+            "InstantiateFunction" => Some(Builtin::InstantiateFunction),
+            "ParseClass" => Some(Builtin::ParseClass),
+            "ParseTemplate" => Some(Builtin::ParseTemplate),
+            "CodeGen Function" => Some(Builtin::CodeGenFunction),
+            "OptFunction" => Some(Builtin::OptFunction),
+            "Optimizer" | "OptModule" | "CodeGenPasses" => Some(Builtin::BackendPhase),
+            _ => None,
+        }
+    }
+
+    fn key(self, trace_event: &TraceEvent) -> Option<&str> {
+        match self {
+            Builtin::BackendPhase => Some(trace_event.name.as_str()),
+            _ => get_detail(trace_event),
+        }
+    }
+
+    /// Name of the global table, also used as `object_facts.kind`
+    fn table_name(self) -> &'static str {
+        match self {
+            Builtin::Source => "source",
+            Builtin::InstantiateClass => "instantiate_class",
+            Builtin::InstantiateFunction => "instantiate_function",
+            Builtin::ParseClass => "parse_class",
+            Builtin::ParseTemplate => "parse_template",
+            Builtin::CodeGenFunction => "codegen_function",
+            Builtin::OptFunction => "opt_function",
+            Builtin::BackendPhase => "backend_phase",
+        }
+    }
+
+    fn from_table_name(name: &str) -> Option<Builtin> {
+        Builtin::ALL
+            .into_iter()
+            .find(|kind| kind.table_name() == name)
+    }
+}
+
+/// Events aggregated per key, into their global table or into `aggregates`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Builtin(Builtin),
+    /// Aggregate from the configuration, by index of its rule
+    Configured(usize),
+}
+
+impl Kind {
+    /// The exclusive duration of an event only excludes its children of the same family
+    fn family(self) -> usize {
+        match self {
+            Kind::Builtin(Builtin::Source) => 0,
+            Kind::Builtin(Builtin::InstantiateClass | Builtin::InstantiateFunction) => 1,
+            Kind::Builtin(Builtin::ParseClass | Builtin::ParseTemplate) => 2,
+            Kind::Builtin(Builtin::CodeGenFunction) => 3,
+            Kind::Builtin(Builtin::OptFunction | Builtin::BackendPhase) => 4,
+            Kind::Configured(index) => 5 + index,
+        }
+    }
+}
+
+/// A span which may still gain a child or a parent, kept on the stack of its thread and family.
+/// Clang writes the children of a span before it, a trace sorted by start after it.
+struct PendingSpan {
+    kind: Kind,
+    key: String,
+    start: u64,
+    end: u64,
+    /// Duration minus the children found so far
+    exclusive: u64,
+    /// Position of the parent on the stack
+    parent: Option<usize>,
+    /// Index in the includes of the thread, for [`Builtin::Source`]
+    include: Option<usize>,
+}
+
+impl PendingSpan {
+    fn contains(&self, other: &PendingSpan) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+}
+
+/// A header included on a thread, its depth is only known at the end of the trace
+struct Include {
+    key: String,
+    parent: Option<usize>,
+    duration: u64,
+    exclusive: u64,
 }
 
+/// Depth of every include of a thread, 1 for the ones without includer
+fn include_depths(includes: &[Include]) -> Vec<i32> {
+    let mut depths: Vec<Option<i32>> = vec![None; includes.len()];
+    for index in 0..includes.len() {
+        let mut chain = Vec::new();
+        let mut current = Some(index);
+        let mut depth = 0;
+        while let Some(include) = current {
+            if let Some(known) = depths[include] {
+                depth = known;
+                break;
+            }
+            chain.push(include);
+            current = includes[include].parent;
+        }
+        for include in chain.into_iter().rev() {
+            depth += 1;
+            depths[include] = Some(depth);
+        }
+    }
+    depths.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+/// A duration in microseconds as stored, the durations longer than `i32::MAX` saturate
+fn saturate(duration: u64) -> i32 {
+    i32::try_from(duration).unwrap_or(i32::MAX)
+}
+
+/// Add the records to the table, or subtract them when `$sign` is -1
 macro_rules! insert_records {
-    ($table:ident, $key:ident, $vec:expr, $conn:ident) => {
-        $conn.transaction::<_, Box<dyn Error + 'static>, _>(|$conn| {
-            for __record in $vec.iter() {
-                diesel::insert_into($table::table)
-                    .values(__record)
-                    .on_conflict($table::$key)
-                    .do_update()
-                    .set((
-                        $table::count.eq($table::count + excluded($table::count)),
-                        $table::duration.eq($table::duration + excluded($table::duration)),
-                    ))
-                    .execute($conn)?;
+    ($table:ident, $record:ident, $key:ident, $records:expr, $sign:expr, $conn:ident) => {
+        for (__key, __aggregate) in $records.iter() {
+            diesel::insert_into($table::table)
+                .values(&$record {
+                    $key: __key,
+                    duration: $sign * __aggregate.duration,
+                    exclusive_duration: $sign * __aggregate.exclusive_duration,
+                    count: $sign * __aggregate.count,
+                })
+                .on_conflict($table::$key)
+                .do_update()
+                .set((
+                    $table::count.eq($table::count + excluded($table::count)),
+                    $table::duration.eq($table::duration + excluded($table::duration)),
+                    $table::exclusive_duration
+                        .eq($table::exclusive_duration + excluded($table::exclusive_duration)),
+                ))
+                .execute($conn)?;
+        }
+    };
+}
+
+/// Metadata of a trace, from its `M` events and top level fields
+#[derive(Default)]
+struct TraceMetadata {
+    process_name: Option<String>,
+    /// The compiler of an output which names no process, e.g. `gcc` for a time report
+    known_compiler: Option<&'static str>,
+    /// From a `--version` line found in the metadata, e.g. `16.0.6`
+    version: Option<String>,
+    /// Per (pid, tid)
+    thread_names: BTreeMap<(u64, u64), String>,
+    beginning_of_time: Option<i64>,
+    display_time_unit: Option<String>,
+}
+
+impl TraceMetadata {
+    fn add_event(&mut self, trace_event: &TraceEvent) {
+        if self.version.is_none() {
+            if let Some(serde_json::Value::Object(args)) = trace_event.args.as_ref() {
+                self.version = args
+                    .values()
+                    .filter_map(serde_json::Value::as_str)
+                    .find_map(version_of)
+                    .map(String::from);
             }
-            Ok(())
-        })?;
+        }
+        let name = match trace_event.args.as_ref().and_then(|args| args.get("name")) {
+            Some(serde_json::Value::String(name)) => name.clone(),
+            _ => return,
+        };
+        match trace_event.name.as_str() {
+            "process_name" => self.process_name = Some(name),
+            "thread_name" => {
+                self.thread_names
+                    .insert((trace_event.pid, trace_event.tid), name);
+            }
+            _ => {}
+        }
+    }
+
+    fn set_header(&mut self, header: TraceHeader) {
+        self.beginning_of_time = header.beginning_of_time;
+        self.display_time_unit = header.display_time_unit;
+    }
+
+    /// Clang names the process after the path it was invoked with, e.g. `/usr/bin/clang-16`
+    fn compiler(&self) -> Option<&str> {
+        self.process_name
+            .as_deref()
+            .map(|name| name.rsplit(['/', '\\']).next().unwrap_or(name))
+            .or(self.known_compiler)
+    }
+
+    /// The version of the metadata, or else the suffix of the compiler, e.g. `16` for `clang-16`
+    fn compiler_version(&self) -> Option<&str> {
+        self.version.as_deref().or_else(|| {
+            let (_, suffix) = self.compiler()?.rsplit_once('-')?;
+            suffix
+                .starts_with(|c: char| c.is_ascii_digit())
+                .then_some(suffix)
+        })
+    }
+}
+
+/// The version a `--version` line names, e.g. `16.0.6` for `Ubuntu clang version 16.0.6 (1)`
+fn version_of(line: &str) -> Option<&str> {
+    let mut words = line.split_whitespace();
+    words.find(|word| *word == "version")?;
+    words
+        .next()
+        .filter(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// Rows per insert statement, small enough to stay below SQLite's limit of bound variables
+pub(crate) const INSERT_BATCH_SIZE: usize = 1000;
+
+/// Insert the rows in batches of [`INSERT_BATCH_SIZE`]
+macro_rules! insert_rows {
+    ($table:ident, $rows:expr, $conn:ident) => {
+        for __batch in $rows.chunks(INSERT_BATCH_SIZE) {
+            diesel::insert_into($table::table)
+                .values(__batch)
+                .execute($conn)?;
+        }
     };
 }
 
-/// Parse the JSON file and store the data into the database
-pub fn json_parser(
-    path: &Path,
-    conn: &mut SqliteConnection,
-) -> Result<(), Box<dyn Error + 'static>> {
-    let json_file = File::open(path)?;
-    let reader = BufReader::new(json_file);
-    let trace_events: TraceEvents = serde_json::from_reader(reader)?;
+/// How traces are parsed
+#[derive(Debug, Default, Clone)]
+pub struct ParseOptions {
+    /// Keep every event in `raw_events`, not only the aggregates
+    pub raw_events: bool,
+    /// Events to sum up in `aggregates`, on top of the built-in tables
+    pub aggregates: AggregateConfig,
+    /// Keep the events read before a trace ends too early instead of failing
+    pub tolerant: bool,
+}
 
-    let object: String = path.with_extension("").as_os_str().to_str().unwrap().into();
+/// An event as found in the trace, for the raw event store
+#[derive(Serialize, Deserialize)]
+struct RawEvent {
+    name: String,
+    category: Option<String>,
+    phase: String,
+    timestamp: u64,
+    duration: Option<u64>,
+    pid: u64,
+    tid: u64,
+    detail: Option<String>,
+    /// `None` when the arguments only hold the detail
+    args: Option<String>,
+}
+
+impl RawEvent {
+    fn new(trace_event: &TraceEvent) -> Self {
+        let detail = get_detail(trace_event).map(String::from);
+        let args = match &trace_event.args {
+            Some(serde_json::Value::Object(args))
+                if args.is_empty() || (args.len() == 1 && detail.is_some()) =>
+            {
+                None
+            }
+            Some(args) => Some(args.to_string()),
+            None => None,
+        };
+        RawEvent {
+            name: trace_event.name.clone(),
+            category: trace_event
+                .category
+                .clone()
+                .filter(|category| !category.is_empty()),
+            phase: trace_event.phase.code().to_string(),
+            timestamp: trace_event.timestamp,
+            duration: trace_event.duration,
+            pid: trace_event.pid,
+            tid: trace_event.tid,
+            detail,
+            args,
+        }
+    }
+}
 
-    let mut frontend: i32 = 0;
-    let mut backend: i32 = 0;
+/// Numbers the spool files of the process
+static SPOOL_FILES: AtomicUsize = AtomicUsize::new(0);
 
-    let mut source_records = Vec::<NewSource>::new();
-    let mut instantiate_class_records = Vec::<NewInstantiateClass>::new();
-    let mut instantiate_function_records = Vec::<NewInstantiateFunction>::new();
-    let mut parse_class_records = Vec::<NewParseClass>::new();
-    let mut parse_template_records = Vec::<NewParseTemplate>::new();
+/// The raw events of a trace, spooled to a temporary file so they are never all in memory
+struct RawEventSpool {
+    path: PathBuf,
+    /// Until the trace is parsed
+    writer: Option<BufWriter<File>>,
+}
 
-    for trace_event in &trace_events.trace_events {
-        match trace_event.name.as_str() {
-            "Source" => {
-                let path_result = get_detail(&trace_event);
-                if let Some(path) = path_result {
-                    source_records.push(NewSource::new(path, get_duration(&trace_event)));
-                }
+impl RawEventSpool {
+    fn create() -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!(
+            "timetracer-raw-events-{}-{}",
+            std::process::id(),
+            SPOOL_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        let writer = BufWriter::new(File::create(&path)?);
+        Ok(RawEventSpool {
+            path,
+            writer: Some(writer),
+        })
+    }
+
+    fn push(&mut self, raw_event: &RawEvent) -> io::Result<()> {
+        if let Some(writer) = &mut self.writer {
+            serde_json::to_writer(&mut *writer, raw_event)?;
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+
+    /// Flush the events once the trace is parsed
+    fn close(&mut self) -> io::Result<()> {
+        match self.writer.take() {
+            Some(mut writer) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Read the events back in the order of the trace, `batch_size` at a time
+    fn for_each_batch(
+        &self,
+        batch_size: usize,
+        mut f: impl FnMut(&[RawEvent]) -> Result<(), Box<dyn Error + 'static>>,
+    ) -> Result<(), Box<dyn Error + 'static>> {
+        let mut batch = Vec::with_capacity(batch_size);
+        for line in BufReader::new(File::open(&self.path)?).lines() {
+            batch.push(serde_json::from_str(&line?)?);
+            if batch.len() == batch_size {
+                f(&batch)?;
+                batch.clear();
             }
-            "InstantiateClass" => {
-                let class_result = get_detail(&trace_event);
-                if let Some(class) = class_result {
-                    instantiate_class_records
-                        .push(NewInstantiateClass::new(class, get_duration(&trace_event)));
-                }
+        }
+        if !batch.is_empty() {
+            f(&batch)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RawEventSpool {
+    fn drop(&mut self) {
+        if let Err(error) = fs::remove_file(&self.path) {
+            debug!("Remove {} error {}", self.path.display(), error);
+        }
+    }
+}
+
+/// What a linker trace contributes on top of the aggregates, stored in place of an object
+#[derive(Default)]
+struct LinkTrace {
+    total_time: i32,
+    /// Inclusive duration per event name
+    phases: BTreeMap<String, Aggregate>,
+    /// Inclusive duration of the ThinLTO backend per module
+    thinlto_modules: BTreeMap<String, Aggregate>,
+}
+
+/// Everything a single trace contributes to the database, aggregated per key
+#[derive(Default)]
+pub struct ParsedTrace {
+    pub object: String,
+    pub frontend: i32,
+    pub backend: i32,
+    /// The trace was truncated, only the events before the truncation are kept
+    pub partial: bool,
+    /// Wall clock time of the first and last event, in microseconds since the UNIX epoch
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+    /// Set when the trace is the one of a linker rather than a compilation
+    link: Option<LinkTrace>,
+    /// Rows of the GCC `-ftime-report`, which the object comes from instead of a trace
+    gcc_passes: BTreeMap<String, Pass>,
+    source: BTreeMap<String, Aggregate>,
+    instantiate_class: BTreeMap<String, Aggregate>,
+    instantiate_function: BTreeMap<String, Aggregate>,
+    parse_class: BTreeMap<String, Aggregate>,
+    parse_template: BTreeMap<String, Aggregate>,
+    codegen_function: BTreeMap<String, Aggregate>,
+    opt_function: BTreeMap<String, Aggregate>,
+    backend_phase: BTreeMap<String, Aggregate>,
+    /// Aggregates from the configuration, keyed by (aggregate, name)
+    configured: BTreeMap<(String, String), Aggregate>,
+    /// Include edges per (includer, included, depth), no includer for the main file
+    include_edges: BTreeMap<(Option<String>, String, i32), Aggregate>,
+    metadata: TraceMetadata,
+    /// Only with [`ParseOptions::raw_events`]
+    raw_events: Option<RawEventSpool>,
+}
+
+impl ParsedTrace {
+    /// The trace is the one of a link, stored in `links` rather than `objects`
+    pub fn is_link(&self) -> bool {
+        self.link.is_some()
+    }
+
+    fn records(&self, kind: Builtin) -> &BTreeMap<String, Aggregate> {
+        match kind {
+            Builtin::Source => &self.source,
+            Builtin::InstantiateClass => &self.instantiate_class,
+            Builtin::InstantiateFunction => &self.instantiate_function,
+            Builtin::ParseClass => &self.parse_class,
+            Builtin::ParseTemplate => &self.parse_template,
+            Builtin::CodeGenFunction => &self.codegen_function,
+            Builtin::OptFunction => &self.opt_function,
+            Builtin::BackendPhase => &self.backend_phase,
+        }
+    }
+
+    fn records_mut(&mut self, kind: Builtin) -> &mut BTreeMap<String, Aggregate> {
+        match kind {
+            Builtin::Source => &mut self.source,
+            Builtin::InstantiateClass => &mut self.instantiate_class,
+            Builtin::InstantiateFunction => &mut self.instantiate_function,
+            Builtin::ParseClass => &mut self.parse_class,
+            Builtin::ParseTemplate => &mut self.parse_template,
+            Builtin::CodeGenFunction => &mut self.codegen_function,
+            Builtin::OptFunction => &mut self.opt_function,
+            Builtin::BackendPhase => &mut self.backend_phase,
+        }
+    }
+
+    /// Store the trace into the database, adding the aggregates to the existing rows
+    pub fn persist(&self, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
+        let total_time = self.frontend.saturating_add(self.backend);
+
+        conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+            match &self.link {
+                Some(link) => self.persist_link(link, conn)?,
+                None => NewObject::new(
+                    &self.object,
+                    total_time,
+                    self.frontend,
+                    self.backend,
+                    self.partial,
+                    self.start_time,
+                    self.end_time,
+                )
+                .insert(conn)?,
             }
-            "InstantiateFunction" => {
-                let function_result = get_detail(&trace_event);
-                if let Some(function) = function_result {
-                    instantiate_function_records.push(NewInstantiateFunction::new(
-                        function,
-                        get_duration(&trace_event),
-                    ));
-                }
+            self.update_aggregates(1, conn)?;
+
+            let include_edges: Vec<NewIncludeEdge> = self
+                .include_edges
+                .iter()
+                .map(|((includer, included, depth), aggregate)| NewIncludeEdge {
+                    object: &self.object,
+                    includer: includer.as_deref(),
+                    included,
+                    depth: *depth,
+                    duration: aggregate.duration,
+                    exclusive_duration: aggregate.exclusive_duration,
+                    count: aggregate.count,
+                })
+                .collect();
+            insert_rows!(include_edges, include_edges, conn);
+            debug!("Persistence include_edges complete");
+
+            let object_facts: Vec<NewObjectFact> = Builtin::ALL
+                .into_iter()
+                .flat_map(|kind| {
+                    self.records(kind)
+                        .iter()
+                        .map(move |(name, aggregate)| NewObjectFact {
+                            object: &self.object,
+                            kind: kind.table_name(),
+                            name,
+                            duration: aggregate.duration,
+                            exclusive_duration: aggregate.exclusive_duration,
+                            count: aggregate.count,
+                        })
+                })
+                .collect();
+            insert_rows!(object_facts, object_facts, conn);
+            debug!("Persistence object_facts complete");
+
+            let object_aggregates: Vec<NewObjectAggregate> = self
+                .configured
+                .iter()
+                .map(|((aggregate, name), record)| NewObjectAggregate {
+                    object: &self.object,
+                    aggregate,
+                    name,
+                    duration: record.duration,
+                    exclusive_duration: record.exclusive_duration,
+                    count: record.count,
+                })
+                .collect();
+            insert_rows!(object_aggregates, object_aggregates, conn);
+            debug!("Persistence object_aggregates complete");
+
+            let gcc_passes: Vec<NewGccPass> = self
+                .gcc_passes
+                .iter()
+                .map(|(name, pass)| NewGccPass {
+                    object: &self.object,
+                    name,
+                    usr: pass.usr,
+                    sys: pass.sys,
+                    wall: pass.wall,
+                    ggc: pass.ggc,
+                })
+                .collect();
+            insert_rows!(gcc_passes, gcc_passes, conn);
+
+            NewTraceMetadata {
+                object: &self.object,
+                compiler: self.metadata.compiler(),
+                process_name: self.metadata.process_name.as_deref(),
+                beginning_of_time: self.metadata.beginning_of_time,
+                display_time_unit: self.metadata.display_time_unit.as_deref(),
+                compiler_version: self.metadata.compiler_version(),
+            }
+            .insert(conn)?;
+            let trace_threads: Vec<NewTraceThread> = self
+                .metadata
+                .thread_names
+                .iter()
+                .map(|((pid, tid), name)| NewTraceThread {
+                    object: &self.object,
+                    pid: *pid as i64,
+                    tid: *tid as i64,
+                    name,
+                })
+                .collect();
+            insert_rows!(trace_threads, trace_threads, conn);
+            debug!("Persistence trace_metadata complete");
+
+            if let Some(spool) = &self.raw_events {
+                diesel::insert_into(raw_event_objects::table)
+                    .values(&NewRawEventObject {
+                        object: &self.object,
+                    })
+                    .execute(conn)?;
+                let object_id = raw_event_objects::table
+                    .filter(raw_event_objects::object.eq(&self.object))
+                    .select(raw_event_objects::id)
+                    .first::<i32>(conn)?;
+                spool.for_each_batch(INSERT_BATCH_SIZE, |batch| {
+                    let rows: Vec<NewRawEvent> = batch
+                        .iter()
+                        .map(|raw_event| NewRawEvent {
+                            object_id,
+                            name: &raw_event.name,
+                            category: raw_event.category.as_deref(),
+                            phase: &raw_event.phase,
+                            ts: raw_event.timestamp as i64,
+                            dur: raw_event.duration.map(|duration| duration as i64),
+                            pid: raw_event.pid as i64,
+                            tid: raw_event.tid as i64,
+                            detail: raw_event.detail.as_deref(),
+                            args: raw_event.args.as_deref(),
+                        })
+                        .collect();
+                    diesel::insert_into(raw_events::table)
+                        .values(&rows)
+                        .execute(conn)?;
+                    Ok(())
+                })?;
+                debug!("Persistence raw_events complete");
+            }
+
+            Ok(())
+        })
+    }
+
+    fn persist_link(
+        &self,
+        link: &LinkTrace,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), Box<dyn Error + 'static>> {
+        diesel::insert_into(links::table)
+            .values(&NewLink {
+                path: &self.object,
+                total_time: link.total_time,
+                partial: self.partial,
+                start_time: self.start_time,
+                end_time: self.end_time,
+            })
+            .execute(conn)?;
+
+        let link_phases: Vec<NewLinkPhase> = link
+            .phases
+            .iter()
+            .map(|(name, aggregate)| NewLinkPhase {
+                link: &self.object,
+                name,
+                duration: aggregate.duration,
+                count: aggregate.count,
+            })
+            .collect();
+        insert_rows!(link_phases, link_phases, conn);
+
+        let thinlto_modules: Vec<NewThinLtoModule> = link
+            .thinlto_modules
+            .iter()
+            .map(|(module, aggregate)| NewThinLtoModule {
+                link: &self.object,
+                module,
+                duration: aggregate.duration,
+                count: aggregate.count,
+            })
+            .collect();
+        insert_rows!(thinlto_modules, thinlto_modules, conn);
+        debug!("Persistence link complete");
+        Ok(())
+    }
+
+    /// Rebuild what a stored object contributed to the global tables from its facts
+    pub fn load(
+        object: &str,
+        conn: &mut SqliteConnection,
+    ) -> Result<ParsedTrace, Box<dyn Error + 'static>> {
+        let mut parsed = ParsedTrace {
+            object: object.to_owned(),
+            ..Default::default()
+        };
+        if let Some(stored) = objects::table
+            .find(object)
+            .first::<Object>(conn)
+            .optional()?
+        {
+            parsed.frontend = stored.frontend;
+            parsed.backend = stored.backend;
+            parsed.partial = stored.partial;
+            parsed.start_time = stored.start_time;
+            parsed.end_time = stored.end_time;
+        }
+        for fact in ObjectFact::load_object(object, conn)? {
+            if let Some(kind) = Builtin::from_table_name(&fact.kind) {
+                parsed.records_mut(kind).insert(
+                    fact.name,
+                    Aggregate {
+                        duration: fact.duration,
+                        exclusive_duration: fact.exclusive_duration,
+                        count: fact.count,
+                    },
+                );
             }
-            "ParseClass" => {
-                let class_result = get_detail(&trace_event);
-                if let Some(class) = class_result {
-                    parse_class_records.push(NewParseClass::new(class, get_duration(&trace_event)));
+        }
+        for stored in ObjectAggregate::load_object(object, conn)? {
+            parsed.configured.insert(
+                (stored.aggregate, stored.name),
+                Aggregate {
+                    duration: stored.duration,
+                    exclusive_duration: stored.exclusive_duration,
+                    count: stored.count,
+                },
+            );
+        }
+        Ok(parsed)
+    }
+
+    /// Undo a previous [`ParsedTrace::persist`], dropping the rows nothing contributes to anymore
+    pub fn retract(&self, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
+        conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+            diesel::delete(objects::table.find(&self.object)).execute(conn)?;
+            diesel::delete(links::table.find(&self.object)).execute(conn)?;
+            diesel::delete(link_phases::table.filter(link_phases::link.eq(&self.object)))
+                .execute(conn)?;
+            diesel::delete(thinlto_modules::table.filter(thinlto_modules::link.eq(&self.object)))
+                .execute(conn)?;
+            diesel::delete(include_edges::table.filter(include_edges::object.eq(&self.object)))
+                .execute(conn)?;
+            diesel::delete(object_facts::table.filter(object_facts::object.eq(&self.object)))
+                .execute(conn)?;
+            diesel::delete(
+                object_aggregates::table.filter(object_aggregates::object.eq(&self.object)),
+            )
+            .execute(conn)?;
+            diesel::delete(gcc_passes::table.filter(gcc_passes::object.eq(&self.object)))
+                .execute(conn)?;
+            diesel::delete(trace_metadata::table.find(&self.object)).execute(conn)?;
+            let raw_object = raw_event_objects::table
+                .filter(raw_event_objects::object.eq(&self.object))
+                .select(raw_event_objects::id);
+            diesel::delete(raw_events::table.filter(raw_events::object_id.eq_any(raw_object)))
+                .execute(conn)?;
+            diesel::delete(
+                raw_event_objects::table.filter(raw_event_objects::object.eq(&self.object)),
+            )
+            .execute(conn)?;
+            diesel::delete(trace_threads::table.filter(trace_threads::object.eq(&self.object)))
+                .execute(conn)?;
+            self.update_aggregates(-1, conn)?;
+
+            diesel::delete(source::table.filter(source::count.le(0))).execute(conn)?;
+            diesel::delete(instantiate_class::table.filter(instantiate_class::count.le(0)))
+                .execute(conn)?;
+            diesel::delete(instantiate_function::table.filter(instantiate_function::count.le(0)))
+                .execute(conn)?;
+            diesel::delete(parse_class::table.filter(parse_class::count.le(0))).execute(conn)?;
+            diesel::delete(parse_template::table.filter(parse_template::count.le(0)))
+                .execute(conn)?;
+            diesel::delete(codegen_function::table.filter(codegen_function::count.le(0)))
+                .execute(conn)?;
+            diesel::delete(opt_function::table.filter(opt_function::count.le(0))).execute(conn)?;
+            diesel::delete(backend_phase::table.filter(backend_phase::count.le(0)))
+                .execute(conn)?;
+            diesel::delete(aggregates::table.filter(aggregates::count.le(0))).execute(conn)?;
+
+            Ok(())
+        })
+    }
+
+    fn update_aggregates(
+        &self,
+        sign: i32,
+        conn: &mut SqliteConnection,
+    ) -> Result<(), Box<dyn Error + 'static>> {
+        insert_records!(source, NewSource, path, self.source, sign, conn);
+        debug!("Persistence source complete");
+
+        insert_records!(
+            instantiate_class,
+            NewInstantiateClass,
+            name,
+            self.instantiate_class,
+            sign,
+            conn
+        );
+        debug!("Persistence instantiate_class complete");
+
+        insert_records!(
+            instantiate_function,
+            NewInstantiateFunction,
+            name,
+            self.instantiate_function,
+            sign,
+            conn
+        );
+        debug!("Persistence instantiate_function complete");
+
+        insert_records!(
+            parse_class,
+            NewParseClass,
+            name,
+            self.parse_class,
+            sign,
+            conn
+        );
+        debug!("Persistence parse_class complete");
+
+        insert_records!(
+            parse_template,
+            NewParseTemplate,
+            name,
+            self.parse_template,
+            sign,
+            conn
+        );
+        debug!("Persistence parse_template complete");
+
+        insert_records!(
+            codegen_function,
+            NewCodeGenFunction,
+            name,
+            self.codegen_function,
+            sign,
+            conn
+        );
+        debug!("Persistence codegen_function complete");
+
+        insert_records!(
+            opt_function,
+            NewOptFunction,
+            name,
+            self.opt_function,
+            sign,
+            conn
+        );
+        debug!("Persistence opt_function complete");
+
+        insert_records!(
+            backend_phase,
+            NewBackendPhase,
+            name,
+            self.backend_phase,
+            sign,
+            conn
+        );
+        debug!("Persistence backend_phase complete");
+
+        for ((aggregate, name), record) in self.configured.iter() {
+            diesel::insert_into(aggregates::table)
+                .values(&NewAggregate {
+                    aggregate,
+                    name,
+                    duration: sign * record.duration,
+                    exclusive_duration: sign * record.exclusive_duration,
+                    count: sign * record.count,
+                })
+                .on_conflict((aggregates::aggregate, aggregates::name))
+                .do_update()
+                .set((
+                    aggregates::count.eq(aggregates::count + excluded(aggregates::count)),
+                    aggregates::duration.eq(aggregates::duration + excluded(aggregates::duration)),
+                    aggregates::exclusive_duration
+                        .eq(aggregates::exclusive_duration
+                            + excluded(aggregates::exclusive_duration)),
+                ))
+                .execute(conn)?;
+        }
+        debug!("Persistence aggregates complete");
+
+        Ok(())
+    }
+}
+
+/// Turn a begin and its matching end event into a complete event
+fn pair_events(mut begin: TraceEvent, end: TraceEvent) -> TraceEvent {
+    begin.phase = EventType::X;
+    begin.duration = Some(end.timestamp.saturating_sub(begin.timestamp));
+    match (&mut begin.args, end.args) {
+        (Some(serde_json::Value::Object(args)), Some(serde_json::Value::Object(end_args))) => {
+            args.extend(end_args)
+        }
+        (args @ None, end_args) => *args = end_args,
+        _ => {}
+    }
+    begin
+}
+
+/// Collects the events of a trace while it is streamed
+#[derive(Default)]
+struct TraceCollector {
+    parsed: ParsedTrace,
+    /// Stack of the pending spans per (pid, tid, family)
+    pending: HashMap<(u64, u64, usize), Vec<PendingSpan>>,
+    /// Includes per (pid, tid)
+    includes: HashMap<(u64, u64), Vec<Include>>,
+    /// Begin events waiting for their end event, per (pid, tid)
+    open: HashMap<(u64, u64), Vec<TraceEvent>>,
+    /// Start of the first complete event and end of the last one
+    bounds: Option<(u64, u64)>,
+    /// Inclusive duration per event name, collected until the trace is known to be a compilation
+    phases: HashMap<String, Aggregate>,
+    /// Inclusive duration of the ThinLTO backend events per module
+    thinlto_modules: HashMap<String, Aggregate>,
+    /// Whether the trace is the one of a link, unknown until an event or the process name tells
+    link: Option<bool>,
+    /// Why the raw events could not be spooled, the trace then fails
+    raw_events_error: Option<io::Error>,
+    options: ParseOptions,
+}
+
+impl TraceCollector {
+    fn new(object: String, options: &ParseOptions) -> Self {
+        let mut collector = TraceCollector {
+            options: options.clone(),
+            ..Default::default()
+        };
+        collector.parsed.object = object;
+        collector
+    }
+
+    fn add_event(&mut self, trace_event: TraceEvent) {
+        if self.options.raw_events {
+            self.spool_raw_event(&trace_event);
+        }
+        let thread = (trace_event.pid, trace_event.tid);
+        match trace_event.phase {
+            EventType::X if trace_event.duration.is_some() => self.add_complete_event(trace_event),
+            EventType::X => debug!(
+                "Complete event {} at {} has no duration",
+                trace_event.name, trace_event.timestamp
+            ),
+            EventType::M => {
+                self.parsed.metadata.add_event(&trace_event);
+                if let Some(process) = self.parsed.metadata.compiler() {
+                    let link = LINKER_PROCESSES.contains(&process);
+                    self.set_link(link);
                 }
             }
-            "ParseTemplate" => {
-                let template_result = get_detail(&trace_event);
-                if let Some(template) = template_result {
-                    parse_template_records
-                        .push(NewParseTemplate::new(template, get_duration(&trace_event)));
+            EventType::B => self.open.entry(thread).or_default().push(trace_event),
+            EventType::E => match self.open.get_mut(&thread).and_then(Vec::pop) {
+                Some(begin) => self.add_complete_event(pair_events(begin, trace_event)),
+                None => debug!("End event at {} has no begin", trace_event.timestamp),
+            },
+            _ => {}
+        }
+    }
+
+    /// Write the event to the raw event spool, created with the first event
+    fn spool_raw_event(&mut self, trace_event: &TraceEvent) {
+        if self.raw_events_error.is_some() {
+            return;
+        }
+        if self.parsed.raw_events.is_none() {
+            match RawEventSpool::create() {
+                Ok(spool) => self.parsed.raw_events = Some(spool),
+                Err(error) => {
+                    self.raw_events_error = Some(error);
+                    return;
                 }
             }
-            "Total Frontend" => {
-                frontend = get_duration(trace_event);
+        }
+        if let Some(spool) = &mut self.parsed.raw_events {
+            if let Err(error) = spool.push(&RawEvent::new(trace_event)) {
+                self.raw_events_error = Some(error);
             }
-            "Total Backend" => {
-                backend = get_duration(trace_event);
+        }
+    }
+
+    /// The record of a span in the parsed trace
+    fn aggregate_mut(&mut self, kind: Kind, key: &str) -> &mut Aggregate {
+        let records = match kind {
+            Kind::Configured(index) => {
+                let aggregate = &self.options.aggregates.aggregates[index].aggregate;
+                return self
+                    .parsed
+                    .configured
+                    .entry((aggregate.clone(), key.to_owned()))
+                    .or_default();
+            }
+            Kind::Builtin(kind) => self.parsed.records_mut(kind),
+        };
+        if !records.contains_key(key) {
+            records.insert(key.to_owned(), Aggregate::default());
+        }
+        records.get_mut(key).unwrap()
+    }
+
+    /// Nest the span with the pending spans of its thread and family, and add it to its record
+    fn add_span(&mut self, kind: Kind, key: String, trace_event: &TraceEvent) {
+        let thread = (trace_event.pid, trace_event.tid);
+        let duration = trace_event.duration.unwrap_or(0);
+        let include = (kind == Kind::Builtin(Builtin::Source)).then(|| {
+            let includes = self.includes.entry(thread).or_default();
+            includes.push(Include {
+                key: key.clone(),
+                parent: None,
+                duration,
+                exclusive: duration,
+            });
+            includes.len() - 1
+        });
+        let mut span = PendingSpan {
+            kind,
+            key,
+            start: trace_event.timestamp,
+            end: trace_event.timestamp.saturating_add(duration),
+            exclusive: duration,
+            parent: None,
+            include,
+        };
+        let family = (thread.0, thread.1, kind.family());
+        let mut stack = std::mem::take(self.pending.entry(family).or_default());
+
+        // The spans it contains ended before it, the ones without a parent become its children
+        while stack.last().is_some_and(|top| span.contains(top)) {
+            let child = stack.pop().unwrap();
+            if child.parent.is_none() {
+                let child_duration = child.end - child.start;
+                span.exclusive = span.exclusive.saturating_sub(child_duration);
+                self.set_include_parent(thread, child.include, span.include);
             }
+        }
+        // The children which ended before it starts can no longer gain a child
+        while stack
+            .last()
+            .is_some_and(|top| top.parent.is_some() && top.end <= span.start)
+        {
+            stack.pop();
+        }
+        // It is a child of a span which came first if the top or one of its ancestors contains it
+        let mut candidate = stack.len().checked_sub(1);
+        while let Some(index) = candidate {
+            if stack[index].contains(&span) {
+                break;
+            }
+            candidate = stack[index].parent;
+        }
+        if let Some(index) = candidate {
+            let parent = &mut stack[index];
+            let exclusive = parent.exclusive.saturating_sub(duration);
+            let removed = saturate(parent.exclusive - exclusive);
+            parent.exclusive = exclusive;
+            let (parent_kind, parent_include) = (parent.kind, parent.include);
+            let aggregate = self.aggregate_mut(parent_kind, &stack[index].key);
+            aggregate.exclusive_duration = aggregate.exclusive_duration.saturating_sub(removed);
+            if let Some(parent_include) = parent_include {
+                self.includes.get_mut(&thread).unwrap()[parent_include].exclusive = exclusive;
+            }
+            self.set_include_parent(thread, span.include, parent_include);
+            span.parent = Some(index);
+        }
+
+        if let Some(include) = span.include {
+            self.includes.get_mut(&thread).unwrap()[include].exclusive = span.exclusive;
+        }
+        let exclusive = span.exclusive;
+        self.aggregate_mut(kind, &span.key).add(duration, exclusive);
+        stack.push(span);
+        *self.pending.get_mut(&family).unwrap() = stack;
+    }
+
+    fn set_include_parent(
+        &mut self,
+        thread: (u64, u64),
+        child: Option<usize>,
+        parent: Option<usize>,
+    ) {
+        if let (Some(child), Some(parent)) = (child, parent) {
+            self.includes.get_mut(&thread).unwrap()[child].parent = Some(parent);
+        }
+    }
+
+    fn set_link(&mut self, link: bool) {
+        if self.link.is_none() {
+            self.link = Some(link);
+            if !link {
+                self.phases = HashMap::new();
+                self.thinlto_modules = HashMap::new();
+            }
+        }
+    }
+
+    fn add_phase(&mut self, trace_event: &TraceEvent) {
+        let name = trace_event.name.as_str();
+        if LINKER_EVENTS.contains(&name) {
+            self.set_link(true);
+        } else if FRONTEND_EVENTS.contains(&name) {
+            self.set_link(false);
+        }
+        // lld names the link only in its last events, the ones before are kept until then
+        if self.link == Some(false) || name.starts_with("Total ") {
+            return;
+        }
+        let duration = get_duration(trace_event);
+        add_inclusive(&mut self.phases, &trace_event.name, duration);
+        if THINLTO_BACKEND_EVENTS.contains(&trace_event.name.as_str()) {
+            if let Some(module) = get_detail(trace_event) {
+                add_inclusive(&mut self.thinlto_modules, module, duration);
+            }
+        }
+    }
+
+    fn add_complete_event(&mut self, trace_event: TraceEvent) {
+        let start = trace_event.timestamp;
+        let end = start.saturating_add(trace_event.duration.unwrap_or(0));
+        self.bounds = Some(match self.bounds {
+            Some((first, last)) => (first.min(start), last.max(end)),
+            None => (start, end),
+        });
+
+        self.add_phase(&trace_event);
+
+        let configured: Vec<(usize, String)> = self
+            .options
+            .aggregates
+            .matching(&trace_event)
+            .filter_map(|(index, rule)| Some((index, rule.key(&trace_event)?.into_owned())))
+            .collect();
+        for (index, key) in configured {
+            self.add_span(Kind::Configured(index), key, &trace_event);
+        }
+
+        if let Some(kind) = Builtin::from_name(&trace_event.name) {
+            if let Some(key) = kind.key(&trace_event) {
+                self.add_span(Kind::Builtin(kind), key.to_owned(), &trace_event);
+            }
+            return;
+        }
+        match trace_event.name.as_str() {
+            "Total Frontend" => self.parsed.frontend = get_duration(&trace_event),
+            "Total Backend" => self.parsed.backend = get_duration(&trace_event),
             _ => {}
         }
     }
 
-    let total_time = frontend + backend;
+    fn finish(mut self) -> ParsedTrace {
+        if let (Some(beginning), Some((first, last))) =
+            (self.parsed.metadata.beginning_of_time, self.bounds)
+        {
+            let at = |offset| {
+                i64::try_from(offset)
+                    .ok()
+                    .and_then(|offset| beginning.checked_add(offset))
+            };
+            self.parsed.start_time = at(first);
+            self.parsed.end_time = at(last);
+        }
+        let link = self.link.unwrap_or_else(|| {
+            self.parsed
+                .metadata
+                .compiler()
+                .is_some_and(|process| LINKER_PROCESSES.contains(&process))
+        });
+        if link && self.parsed.frontend == 0 && self.parsed.backend == 0 {
+            let total_time = match self.phases.get("ExecuteLinker") {
+                Some(execute) => execute.duration,
+                None => self.bounds.map_or(0, |(first, last)| {
+                    i32::try_from(last - first).unwrap_or(i32::MAX)
+                }),
+            };
+            self.parsed.link = Some(LinkTrace {
+                total_time,
+                phases: self.phases.drain().collect(),
+                thinlto_modules: self.thinlto_modules.drain().collect(),
+            });
+        }
+        for (thread, open) in &self.open {
+            if !open.is_empty() {
+                debug!("{} begin events never ended on {:?}", open.len(), thread);
+            }
+        }
+        for includes in self.includes.values() {
+            let depths = include_depths(includes);
+            for (include, depth) in includes.iter().zip(depths) {
+                let includer = include.parent.map(|parent| includes[parent].key.clone());
+                self.parsed
+                    .include_edges
+                    .entry((includer, include.key.clone(), depth))
+                    .or_default()
+                    .add(include.duration, include.exclusive);
+            }
+        }
+        self.parsed
+    }
+}
+
+/// Add an event to the inclusive duration and count of its key, only allocating new keys
+fn add_inclusive(records: &mut HashMap<String, Aggregate>, key: &str, duration: i32) {
+    if !records.contains_key(key) {
+        records.insert(key.to_owned(), Aggregate::default());
+    }
+    let aggregate = records.get_mut(key).unwrap();
+    aggregate.duration = aggregate.duration.saturating_add(duration);
+    aggregate.count = aggregate.count.saturating_add(1);
+}
+
+/// Extensions of the compressed traces which are decompressed on the fly
+pub const COMPRESSION_EXTENSIONS: [&str; 3] = ["gz", "zst", "xz"];
+
+pub(crate) fn compression_extension(path: &Path) -> Option<&str> {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .filter(|extension| COMPRESSION_EXTENSIONS.contains(extension))
+}
+
+/// Extension of the captured outputs of GCC `-ftime-report`, parsed instead of a trace
+pub const TIME_REPORT_EXTENSION: &str = "time-report";
+
+pub(crate) fn is_time_report(path: &Path) -> bool {
+    let path = match compression_extension(path) {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    };
+    path.extension()
+        .is_some_and(|extension| extension == TIME_REPORT_EXTENSION)
+}
+
+/// Name of the object a trace belongs to, the trace path without its extensions
+pub fn object_name(path: &Path) -> String {
+    let path = match compression_extension(path) {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    };
+    path.with_extension("").as_os_str().to_str().unwrap().into()
+}
+
+/// Open a trace, decompressing it according to its extension
+pub fn open_trace(path: &Path) -> io::Result<Box<dyn Read + Send>> {
+    let file = File::open(path)?;
+    Ok(match compression_extension(path) {
+        Some("gz") => Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+        Some("zst") => Box::new(BufReader::new(zstd::stream::read::Decoder::new(file)?)),
+        Some("xz") => Box::new(BufReader::new(xz2::read::XzDecoder::new(file))),
+        _ => Box::new(BufReader::new(file)),
+    })
+}
+
+/// Parse the JSON file into per key aggregates, without touching the database
+pub fn parse_trace(
+    path: &Path,
+    options: &ParseOptions,
+) -> Result<ParsedTrace, Box<dyn Error + Send + Sync + 'static>> {
+    if is_time_report(path) {
+        let reader = BufReader::new(open_trace(path)?);
+        return parse_time_report_from(reader, object_name(path), options);
+    }
+    parse_trace_from(open_trace(path)?, object_name(path), options)
+}
+
+/// Parse the GCC `-ftime-report` output of `object`
+pub fn parse_time_report_from<R: BufRead>(
+    reader: R,
+    object: String,
+    options: &ParseOptions,
+) -> Result<ParsedTrace, Box<dyn Error + Send + Sync + 'static>> {
+    let report = time_report::parse(reader)?;
+    let partial = report.total.is_none();
+    if partial {
+        if !options.tolerant {
+            return Err(format!("Time report {} has no TOTAL, it is truncated", object).into());
+        }
+        warn!("Time report {} is truncated", object);
+    }
     debug!(
         "Parse completed {}, total compile time {}",
-        object, total_time
+        object,
+        report.frontend() + report.backend()
     );
 
-    NewObject::new(&object, total_time, frontend, backend).insert(conn)?;
+    Ok(ParsedTrace {
+        frontend: report.frontend(),
+        backend: report.backend(),
+        partial,
+        gcc_passes: report.passes,
+        metadata: TraceMetadata {
+            known_compiler: Some("gcc"),
+            ..Default::default()
+        },
+        object,
+        ..Default::default()
+    })
+}
 
-    insert_records!(source, path, source_records, conn);
-    debug!("Persistence source complete");
+/// Parse a trace which is not read from a file of its own, e.g. from stdin, as `object`
+pub fn parse_trace_from<R: Read>(
+    reader: R,
+    object: String,
+    options: &ParseOptions,
+) -> Result<ParsedTrace, Box<dyn Error + Send + Sync + 'static>> {
+    let mut collector = TraceCollector::new(object, options);
 
-    insert_records!(instantiate_class, name, instantiate_class_records, conn);
-    debug!("Persistence instantiate_class complete");
+    match stream_trace_events(reader, |trace_event| collector.add_event(trace_event)) {
+        Ok(header) => collector.parsed.metadata.set_header(header),
+        Err(error) if options.tolerant && is_truncated(&error) => {
+            warn!("Trace {} is truncated, {}", collector.parsed.object, error);
+            collector.parsed.partial = true;
+        }
+        Err(error) => return Err(error.into()),
+    }
+    if let Some(error) = collector.raw_events_error.take() {
+        return Err(error.into());
+    }
+    if let Some(spool) = &mut collector.parsed.raw_events {
+        spool.close()?;
+    }
+    let parsed = collector.finish();
 
-    insert_records!(
-        instantiate_function,
-        name,
-        instantiate_function_records,
-        conn
+    debug!(
+        "Parse completed {}, total compile time {}",
+        parsed.object,
+        parsed.frontend.saturating_add(parsed.backend)
     );
-    debug!("Persistence instantiate_function complete");
 
-    insert_records!(parse_class, name, parse_class_records, conn);
-    debug!("Persistence parse_class complete");
+    Ok(parsed)
+}
+
+/// Parse the JSON file and store the data into the database
+pub fn json_parser(
+    path: &Path,
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error + 'static>> {
+    parse_trace(path, &ParseOptions::default())
+        .map_err(|e| e as Box<dyn Error + 'static>)?
+        .persist(conn)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{io::Write, path::Path};
+
+    use diesel::prelude::*;
+
+    use super::{
+        object_name, parse_trace, parse_trace_from, ParseOptions, ParsedTrace, TraceCollector,
+    };
+    use crate::{
+        schema::{
+            backend_phase, gcc_passes, include_edges, link_phases, links, object_facts, objects,
+            raw_event_objects, raw_events, thinlto_modules, trace_metadata,
+        },
+        test_dir::TestDir,
+        trace_event::stream_trace_events,
+        tracedb::{get_connection, RawEvent},
+    };
+
+    #[test]
+    fn test_exclusive_duration() {
+        // a.h includes b.h and c.h, b.h includes d.h; the class parsed inside d.h belongs to
+        // a different family and is not subtracted from it
+        let trace = r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 20, "name": "Source", "args": {"detail": "d.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 12, "dur": 5, "name": "ParseClass", "args": {"detail": "D"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 5, "dur": 40, "name": "Source", "args": {"detail": "b.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 50, "dur": 30, "name": "Source", "args": {"detail": "c.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 100, "name": "Source", "args": {"detail": "a.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 100, "name": "InstantiateClass", "args": {"detail": "A"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 60, "name": "InstantiateFunction", "args": {"detail": "f"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 20, "dur": 10, "name": "InstantiateClass", "args": {"detail": "A"}}
+        ]}"#;
+        let mut collector = TraceCollector::default();
+        stream_trace_events(trace.as_bytes(), |event| collector.add_event(event)).unwrap();
+        let parsed = collector.finish();
+
+        let source = |key: &str| {
+            let aggregate = &parsed.source[key];
+            (aggregate.duration, aggregate.exclusive_duration)
+        };
+        assert_eq!(source("a.h"), (100, 30));
+        assert_eq!(source("b.h"), (40, 20));
+        assert_eq!(source("c.h"), (30, 30));
+        assert_eq!(source("d.h"), (20, 20));
+        assert_eq!(parsed.parse_class["D"].exclusive_duration, 5);
+
+        let class = &parsed.instantiate_class["A"];
+        assert_eq!(
+            (class.duration, class.exclusive_duration, class.count),
+            (110, 50, 2)
+        );
+        assert_eq!(parsed.instantiate_function["f"].exclusive_duration, 50);
+
+        let edge = |includer: Option<&str>, included: &str, depth: i32| {
+            let aggregate =
+                &parsed.include_edges[&(includer.map(String::from), included.to_string(), depth)];
+            (aggregate.duration, aggregate.exclusive_duration)
+        };
+        assert_eq!(parsed.include_edges.len(), 4);
+        assert_eq!(edge(None, "a.h", 1), (100, 30));
+        assert_eq!(edge(Some("a.h"), "b.h", 2), (40, 20));
+        assert_eq!(edge(Some("b.h"), "d.h", 3), (20, 20));
+
+        // The edges are stored per object and go away with it
+        let mut conn = get_connection(":memory:").unwrap();
+        parsed.persist(&mut conn).unwrap();
+        assert_eq!(include_edges::table.count().get_result(&mut conn), Ok(4));
+        parsed.retract(&mut conn).unwrap();
+        assert_eq!(include_edges::table.count().get_result(&mut conn), Ok(0));
+    }
+
+    #[test]
+    fn test_nesting_order() {
+        // The includes of test_exclusive_duration sorted by start, and a header taking longer
+        // than i32::MAX microseconds on another thread
+        let trace = r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 100, "name": "Source", "args": {"detail": "a.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 5, "dur": 40, "name": "Source", "args": {"detail": "b.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 20, "name": "Source", "args": {"detail": "d.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 50, "dur": 30, "name": "Source", "args": {"detail": "c.h"}},
+            {"pid": 1, "tid": 2, "ph": "X", "ts": 0, "dur": 5000000000, "name": "Source", "args": {"detail": "e.h"}},
+            {"pid": 1, "tid": 2, "ph": "X", "ts": 5000000000, "dur": 5000000000, "name": "Source", "args": {"detail": "e.h"}}
+        ]}"#;
+        let mut collector = TraceCollector::default();
+        stream_trace_events(trace.as_bytes(), |event| collector.add_event(event)).unwrap();
+        // Only the spans without parent and the ones which may still gain a child are kept
+        let pending = |tid| collector.pending[&(1, tid, 0)].len();
+        assert_eq!((pending(1), pending(2)), (2, 2));
+        let parsed = collector.finish();
+
+        let source = |key: &str| {
+            let aggregate = &parsed.source[key];
+            (aggregate.duration, aggregate.exclusive_duration)
+        };
+        assert_eq!(source("a.h"), (100, 30));
+        assert_eq!(source("b.h"), (40, 20));
+        assert_eq!(source("d.h"), (20, 20));
+        assert_eq!(source("e.h"), (i32::MAX, i32::MAX));
+        assert_eq!(parsed.include_edges.len(), 5);
+        let edge = &parsed.include_edges[&(Some("b.h".to_string()), "d.h".to_string(), 3)];
+        assert_eq!(edge.exclusive_duration, 20);
+    }
+
+    #[test]
+    fn test_backend_events() {
+        let trace = r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 30, "name": "OptFunction", "args": {"detail": "f"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 90, "name": "OptModule", "args": {"detail": "a.cpp"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 100, "name": "Optimizer"},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 100, "dur": 50, "name": "CodeGenPasses"},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 8, "name": "CodeGen Function", "args": {"detail": "f"}}
+        ]}"#;
+        let mut collector = TraceCollector::default();
+        stream_trace_events(trace.as_bytes(), |event| collector.add_event(event)).unwrap();
+        let parsed = collector.finish();
 
-    insert_records!(parse_template, name, parse_template_records, conn);
-    debug!("Persistence parse_template complete");
+        let phase = |key: &str| {
+            let aggregate = &parsed.backend_phase[key];
+            (aggregate.duration, aggregate.exclusive_duration)
+        };
+        assert_eq!(phase("Optimizer"), (100, 10));
+        assert_eq!(phase("OptModule"), (90, 60));
+        assert_eq!(phase("CodeGenPasses"), (50, 50));
+        assert_eq!(parsed.opt_function["f"].exclusive_duration, 30);
+        assert_eq!(parsed.codegen_function["f"].duration, 8);
 
-    Ok(())
+        // Retracting the only object drops its facts and the rollups they add up to
+        let mut conn = get_connection(":memory:").unwrap();
+        parsed.persist(&mut conn).unwrap();
+        assert_eq!(object_facts::table.count().get_result(&mut conn), Ok(5));
+        parsed.retract(&mut conn).unwrap();
+        assert_eq!(object_facts::table.count().get_result(&mut conn), Ok(0));
+        assert_eq!(backend_phase::table.count().get_result(&mut conn), Ok(0));
+    }
+
+    #[test]
+    fn test_configured_aggregates() {
+        let trace = r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 100, "name": "Frontend", "args": {"detail": "a.cpp"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 40, "name": "Source", "args": {"detail": "a.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 60, "dur": 20, "name": "Frontend", "args": {"detail": "a.cpp"}}
+        ]}"#;
+        let options = ParseOptions {
+            aggregates: serde_json::from_str(
+                r#"{"aggregates": [
+                    {"aggregate": "frontend", "name": "Frontend", "key": "args.detail"},
+                    {"aggregate": "headers", "name": "Source"}
+                ]}"#,
+            )
+            .unwrap(),
+            ..Default::default()
+        };
+        let mut collector = TraceCollector::new("a.o".to_string(), &options);
+        stream_trace_events(trace.as_bytes(), |event| collector.add_event(event)).unwrap();
+        let parsed = collector.finish();
+
+        let configured = |aggregate: &str, name: &str| {
+            let record = &parsed.configured[&(aggregate.to_string(), name.to_string())];
+            (record.duration, record.exclusive_duration, record.count)
+        };
+        assert_eq!(configured("frontend", "a.cpp"), (120, 100, 2));
+        assert_eq!(configured("headers", "Source"), (40, 40, 1));
+        // The built-in tables are still filled
+        assert_eq!(parsed.source["a.h"].duration, 40);
+    }
+
+    #[test]
+    fn test_begin_end_events() {
+        let trace = r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "B", "ts": 0, "name": "Source", "args": {"detail": "a.h"}},
+            {"pid": 1, "tid": 2, "ph": "B", "ts": 5, "name": "Source", "args": {"detail": "c.h"}},
+            {"pid": 1, "tid": 1, "ph": "B", "ts": 10, "name": "Source", "args": {"detail": "b.h"}},
+            {"pid": 1, "tid": 1, "ph": "E", "ts": 30},
+            {"pid": 1, "tid": 2, "ph": "E", "ts": 8},
+            {"pid": 1, "tid": 1, "ph": "E", "ts": 100},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 100, "name": "Source", "args": {"detail": "d.h"}},
+            {"pid": 1, "tid": 1, "ph": "B", "ts": 0, "name": "Total Frontend"},
+            {"pid": 1, "tid": 1, "ph": "E", "ts": 120}
+        ]}"#;
+        let mut collector = TraceCollector::default();
+        stream_trace_events(trace.as_bytes(), |event| collector.add_event(event)).unwrap();
+        let parsed = collector.finish();
+
+        let source = |key: &str| {
+            let aggregate = &parsed.source[key];
+            (aggregate.duration, aggregate.exclusive_duration)
+        };
+        assert_eq!(source("a.h"), (100, 80));
+        assert_eq!(source("b.h"), (20, 20));
+        assert_eq!(source("c.h"), (3, 3));
+        assert!(!parsed.source.contains_key("d.h"));
+        assert_eq!(parsed.frontend, 120);
+    }
+
+    #[test]
+    fn test_metadata() {
+        let trace = r#"{"traceEvents": [
+            {"pid": 7, "tid": 7, "ph": "M", "ts": 0, "cat": "", "name": "process_name", "args": {"name": "/usr/lib/llvm-16/bin/clang-16"}},
+            {"pid": 7, "tid": 7, "ph": "M", "ts": 0, "cat": "", "name": "thread_name", "args": {"name": "clang-16"}},
+            {"pid": 7, "tid": 7, "ph": "X", "ts": 20, "dur": 100, "name": "Total Frontend"},
+            {"pid": 7, "tid": 7, "ph": "X", "ts": 120, "dur": 50, "name": "Total Backend"}
+        ], "beginningOfTime": 1700000000000000}"#;
+        let mut collector = TraceCollector::default();
+        let header =
+            stream_trace_events(trace.as_bytes(), |event| collector.add_event(event)).unwrap();
+        collector.parsed.metadata.set_header(header);
+        let parsed = collector.finish();
+
+        assert_eq!(parsed.metadata.compiler(), Some("clang-16"));
+        assert_eq!(parsed.metadata.compiler_version(), Some("16"));
+        assert_eq!(parsed.metadata.thread_names[&(7, 7)], "clang-16");
+        assert_eq!(parsed.metadata.beginning_of_time, Some(1700000000000000));
+        assert_eq!(parsed.metadata.display_time_unit, None);
+        assert_eq!(parsed.start_time, Some(1700000000000020));
+        assert_eq!(parsed.end_time, Some(1700000000000170));
+
+        // Times past what fits are left out rather than overflowing
+        let trace = r#"{"traceEvents": [
+            {"pid": 7, "tid": 7, "ph": "X", "ts": 18446744073709551000, "dur": 1000, "name": "Total Frontend"}
+        ], "beginningOfTime": 1700000000000000}"#;
+        let mut collector = TraceCollector::default();
+        let header =
+            stream_trace_events(trace.as_bytes(), |event| collector.add_event(event)).unwrap();
+        collector.parsed.metadata.set_header(header);
+        let parsed = collector.finish();
+        assert_eq!((parsed.start_time, parsed.end_time), (None, None));
+
+        // The version of a `--version` line wins over the suffix of the compiler
+        let trace = r#"{"traceEvents": [
+            {"pid": 7, "tid": 7, "ph": "M", "ts": 0, "name": "process_name", "args": {"name": "clang-16"}},
+            {"pid": 7, "tid": 7, "ph": "M", "ts": 0, "name": "process_labels", "args": {"labels": "Ubuntu clang version 16.0.6 (15)"}}
+        ]}"#;
+        let parsed = parse_trace_from(trace.as_bytes(), "a.o".to_string(), &Default::default());
+        assert_eq!(parsed.unwrap().metadata.compiler_version(), Some("16.0.6"));
+    }
+
+    #[test]
+    fn test_compressed_traces() {
+        let trace = r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "name": "Source", "args": {"detail": "a.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 30, "name": "Total Frontend"}
+        ]}"#;
+        let directory = TestDir::new("compressed");
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(trace.as_bytes()).unwrap();
+        let mut xz = xz2::write::XzEncoder::new(Vec::new(), 6);
+        xz.write_all(trace.as_bytes()).unwrap();
+        for (extension, content) in [
+            ("gz", gz.finish().unwrap()),
+            ("zst", zstd::encode_all(trace.as_bytes(), 0).unwrap()),
+            ("xz", xz.finish().unwrap()),
+        ] {
+            let path = directory.write(&format!("a.cpp.json.{}", extension), content);
+            let parsed = parse_trace(&path, &ParseOptions::default()).unwrap();
+            assert_eq!(
+                parsed.object,
+                directory.path().join("a.cpp").to_str().unwrap()
+            );
+            assert_eq!(parsed.frontend, 30);
+            assert_eq!(parsed.source["a.h"].duration, 10);
+        }
+
+        // A trace named after a compression it is not in, or cut in the middle of the stream
+        let plain = directory.write("b.cpp.json.gz", trace);
+        assert!(parse_trace(&plain, &ParseOptions::default()).is_err());
+        let content = zstd::encode_all(trace.as_bytes(), 0).unwrap();
+        let cut = directory.write("c.cpp.json.zst", &content[..content.len() / 2]);
+        assert!(parse_trace(&cut, &ParseOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_object_name() {
+        assert_eq!(
+            object_name(Path::new("/build/foo.cpp.json")),
+            "/build/foo.cpp"
+        );
+        assert_eq!(
+            object_name(Path::new("/build/foo.cpp.json.gz")),
+            "/build/foo.cpp"
+        );
+        assert_eq!(
+            object_name(Path::new("/build/foo.cpp.json.zst")),
+            "/build/foo.cpp"
+        );
+    }
+
+    #[test]
+    fn test_raw_events() {
+        let trace = r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "cat": "", "name": "Source", "args": {"detail": "a.h"}},
+            {"pid": 1, "tid": 1, "ph": "i", "ts": 5, "name": "Marker", "args": {"detail": "m", "kind": 2}}
+        ]}"#;
+        let options = ParseOptions {
+            raw_events: true,
+            ..Default::default()
+        };
+        let parsed = parse_trace_from(trace.as_bytes(), "object".to_string(), &options).unwrap();
+        let mut conn = get_connection(":memory:").unwrap();
+        parsed.persist(&mut conn).unwrap();
+
+        let object_id: i32 = raw_event_objects::table
+            .filter(raw_event_objects::object.eq("object"))
+            .select(raw_event_objects::id)
+            .first(&mut conn)
+            .unwrap();
+        let raw_events: Vec<RawEvent> = raw_events::table
+            .order(raw_events::id)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(raw_events.len(), 2);
+        assert!(raw_events.iter().all(|event| event.object_id == object_id));
+        let source = &raw_events[0];
+        assert_eq!((source.phase.as_str(), source.dur), ("X", Some(10)));
+        assert_eq!(
+            (source.category.as_deref(), source.args.as_deref()),
+            (None, None)
+        );
+        let marker = &raw_events[1];
+        assert_eq!(
+            (marker.phase.as_str(), marker.detail.as_deref()),
+            ("i", Some("m"))
+        );
+        assert_eq!(marker.args.as_deref(), Some(r#"{"detail":"m","kind":2}"#));
+
+        // The spool is gone once the trace is dropped, the rows once it is retracted
+        let spool = parsed.raw_events.as_ref().unwrap().path.clone();
+        parsed.retract(&mut conn).unwrap();
+        drop(parsed);
+        assert!(!spool.exists());
+        assert_eq!(raw_events::table.count().get_result(&mut conn), Ok(0));
+        assert_eq!(
+            raw_event_objects::table.count().get_result(&mut conn),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn test_truncated_trace() {
+        let directory = TestDir::new("truncated");
+        let path = directory.write(
+            "a.json",
+            r#"{"traceEvents": [
+                {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "name": "Source", "args": {"detail": "a.h"}},
+                {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 5, "name": "Source", "args": {"det"#,
+        );
+
+        assert!(parse_trace(&path, &ParseOptions::default()).is_err());
+        let options = ParseOptions {
+            tolerant: true,
+            ..Default::default()
+        };
+        let parsed = parse_trace(&path, &options).unwrap();
+        assert!(parsed.partial);
+        assert_eq!(parsed.source.len(), 1);
+        assert_eq!(parsed.source["a.h"].duration, 10);
+    }
+
+    #[test]
+    fn test_link_trace() {
+        let trace = r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 1000, "name": "ExecuteLinker"},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 100, "name": "Parse input files"},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 110, "dur": 700, "name": "LTO"},
+            {"pid": 1, "tid": 2, "ph": "X", "ts": 120, "dur": 300, "name": "Thin backend", "args": {"detail": "a.o"}},
+            {"pid": 1, "tid": 3, "ph": "X", "ts": 120, "dur": 500, "name": "Thin backend", "args": {"detail": "b.o"}},
+            {"pid": 1, "tid": 3, "ph": "X", "ts": 130, "dur": 200, "name": "OptFunction", "args": {"detail": "main"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 820, "dur": 150, "name": "Write output file"},
+            {"pid": 1, "tid": 0, "ph": "X", "ts": 0, "dur": 1000, "name": "Total ExecuteLinker"},
+            {"pid": 1, "tid": 0, "ph": "M", "ts": 0, "name": "process_name", "args": {"name": "ld.lld"}}
+        ]}"#;
+        let parsed = parse_trace_from(
+            trace.as_bytes(),
+            "app".to_string(),
+            &ParseOptions::default(),
+        )
+        .unwrap();
+        assert!(parsed.is_link());
+        assert_eq!(parsed.opt_function["main"].duration, 200);
+
+        let mut conn = get_connection(":memory:").unwrap();
+        parsed.persist(&mut conn).unwrap();
+        assert_eq!(objects::table.count().get_result::<i64>(&mut conn), Ok(0));
+        assert_eq!(
+            links::table
+                .select(links::total_time)
+                .first::<i32>(&mut conn),
+            Ok(1000)
+        );
+        let phases: Vec<(String, i32)> = link_phases::table
+            .select((link_phases::name, link_phases::duration))
+            .order(link_phases::name)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(phases.len(), 6);
+        assert_eq!(phases[1], ("LTO".to_string(), 700));
+        let modules: Vec<(String, i32)> = thinlto_modules::table
+            .select((thinlto_modules::module, thinlto_modules::duration))
+            .order(thinlto_modules::module)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(
+            modules,
+            vec![("a.o".to_string(), 300), ("b.o".to_string(), 500)]
+        );
+
+        ParsedTrace::load("app", &mut conn)
+            .unwrap()
+            .retract(&mut conn)
+            .unwrap();
+        assert_eq!(links::table.count().get_result::<i64>(&mut conn), Ok(0));
+        assert_eq!(
+            link_phases::table.count().get_result::<i64>(&mut conn),
+            Ok(0)
+        );
+        assert_eq!(
+            thinlto_modules::table.count().get_result::<i64>(&mut conn),
+            Ok(0)
+        );
+    }
+
+    #[test]
+    fn test_link_phases() {
+        let events = |trace: &str| -> TraceCollector {
+            let mut collector = TraceCollector::new("a.o".to_string(), &ParseOptions::default());
+            stream_trace_events(trace.as_bytes(), |event| collector.add_event(event)).unwrap();
+            collector
+        };
+
+        let compile = events(
+            r#"{"traceEvents": [
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "name": "Source", "args": {"detail": "a.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 20, "dur": 10, "name": "LTO"},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 30, "dur": 10, "name": "Write output file"}
+        ]}"#,
+        );
+        assert!(compile.phases.is_empty());
+        assert!(!compile.finish().is_link());
+
+        let link = events(
+            r#"{"traceEvents": [
+            {"pid": 1, "tid": 0, "ph": "M", "ts": 0, "name": "process_name", "args": {"name": "ld.lld"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "name": "Load input files"},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 20, "dur": 10, "name": "Source", "args": {"detail": "a.h"}}
+        ]}"#,
+        );
+        assert_eq!(link.phases.len(), 2);
+        let parsed = link.finish();
+        assert!(parsed.is_link());
+        assert_eq!(parsed.link.unwrap().total_time, 30);
+    }
+
+    #[test]
+    fn test_time_report() {
+        let directory = TestDir::new("time-report");
+        let path = directory.write(
+            "a.cpp.time-report",
+            "Time variable                                   usr           sys          wall           GGC
+ phase parsing                      :   0.30 ( 38%)   0.10 ( 56%)   0.41 ( 41%) 50000k ( 60%)
+ phase opt and generate             :   0.45 ( 57%)   0.08 ( 44%)   0.52 ( 53%) 27000k ( 33%)
+ |name lookup                       :   0.05 (  6%)   0.01 (  6%)   0.07 (  7%)  2412k (  3%)
+ TOTAL                              :   0.79          0.18          0.99         83000k
+",
+        );
+        let parsed = parse_trace(&path, &ParseOptions::default()).unwrap();
+        assert_eq!(parsed.object, object_name(&path));
+        assert_eq!((parsed.frontend, parsed.backend), (410000, 520000));
+
+        let mut conn = get_connection(":memory:").unwrap();
+        parsed.persist(&mut conn).unwrap();
+        assert_eq!(
+            objects::table
+                .select(objects::total_time)
+                .first::<i32>(&mut conn),
+            Ok(930000)
+        );
+        let passes: Vec<(String, i32)> = gcc_passes::table
+            .select((gcc_passes::name, gcc_passes::wall))
+            .order(gcc_passes::name)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(passes[0], ("name lookup".to_string(), 70000));
+        assert_eq!(passes.len(), 3);
+        assert_eq!(
+            trace_metadata::table
+                .select(trace_metadata::compiler)
+                .first::<Option<String>>(&mut conn),
+            Ok(Some("gcc".to_string()))
+        );
+
+        ParsedTrace::load(&parsed.object, &mut conn)
+            .unwrap()
+            .retract(&mut conn)
+            .unwrap();
+        assert_eq!(
+            gcc_passes::table.count().get_result::<i64>(&mut conn),
+            Ok(0)
+        );
+
+        // A report cut before its TOTAL is only parsed when tolerant, diagnostics alone never
+        let truncated = directory.write(
+            "b.cpp.time-report",
+            "Time variable                                   usr           sys          wall           GGC
+ phase parsing                      :   0.30 ( 38%)   0.10 ( 56%)   0.41 ( 41%) 50000k ( 60%)
+",
+        );
+        assert!(parse_trace(&truncated, &ParseOptions::default()).is_err());
+        let options = ParseOptions {
+            tolerant: true,
+            ..Default::default()
+        };
+        let parsed = parse_trace(&truncated, &options).unwrap();
+        assert!(parsed.partial);
+        assert_eq!(parsed.frontend, 410000);
+        let diagnostics = directory.write(
+            "c.cpp.time-report",
+            "c.cpp:1:10: fatal error: missing.h: No such file or directory\n",
+        );
+        assert!(parse_trace(&diagnostics, &options).is_err());
+    }
 }
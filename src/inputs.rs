@@ -0,0 +1,133 @@
+/// Traces named on the command line: paths, `@file` response files, or `-` for stdin
+use std::{
+    fs,
+    io::{self, BufRead},
+    path::PathBuf,
+};
+
+/// What the command line arguments expand to
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Inputs {
+    /// Trace files, in the order they were given
+    pub paths: Vec<PathBuf>,
+    /// Stdin holds a trace, left unread past its leading whitespace so it is parsed as it streams
+    pub stdin_trace: bool,
+}
+
+/// The paths of a response file or of stdin, one per line; blank lines are ignored
+fn list_paths(content: &str) -> impl Iterator<Item = PathBuf> + '_ {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Whether stdin holds a trace rather than a list of paths, consuming only its whitespace
+fn is_trace<R: BufRead>(stdin: &mut R) -> io::Result<bool> {
+    loop {
+        let buffer = stdin.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(false);
+        }
+        let whitespace = buffer
+            .iter()
+            .take_while(|byte| byte.is_ascii_whitespace())
+            .count();
+        if let Some(first) = buffer.get(whitespace) {
+            let trace = matches!(first, b'{' | b'[');
+            stdin.consume(whitespace);
+            return Ok(trace);
+        }
+        stdin.consume(whitespace);
+    }
+}
+
+/// Expand the arguments into the traces to ingest, `stdin` is only read if `-` is given
+pub fn collect_inputs<R: BufRead>(arguments: &[String], stdin: &mut R) -> io::Result<Inputs> {
+    let mut inputs = Inputs::default();
+    let mut stdin_read = false;
+    for argument in arguments {
+        if argument == "-" {
+            if stdin_read {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "stdin can only be given once",
+                ));
+            }
+            stdin_read = true;
+
+            if is_trace(stdin)? {
+                inputs.stdin_trace = true;
+            } else {
+                let mut content = String::new();
+                stdin.read_to_string(&mut content)?;
+                inputs.paths.extend(list_paths(&content));
+            }
+        } else if let Some(response_file) = argument.strip_prefix('@') {
+            inputs
+                .paths
+                .extend(list_paths(&fs::read_to_string(response_file)?));
+        } else {
+            inputs.paths.push(PathBuf::from(argument));
+        }
+    }
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{ErrorKind, Read},
+        path::PathBuf,
+    };
+
+    use super::collect_inputs;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_collect_inputs() {
+        let directory = TestDir::new("inputs");
+        let response_file = directory.write("traces.rsp", "b.json\n\n  c.json.gz  \n");
+
+        let arguments = vec![
+            "a.json".to_string(),
+            format!("@{}", response_file.display()),
+            "-".to_string(),
+        ];
+        let inputs = collect_inputs(&arguments, &mut "d.json\ne.json\n".as_bytes()).unwrap();
+        assert_eq!(
+            inputs.paths,
+            ["a.json", "b.json", "c.json.gz", "d.json", "e.json"]
+                .map(PathBuf::from)
+                .to_vec()
+        );
+        assert!(!inputs.stdin_trace);
+
+        // The trace is left on stdin for the parser
+        let mut stdin = " \n{\"traceEvents\": []}".as_bytes();
+        let inputs = collect_inputs(&["-".to_string()], &mut stdin).unwrap();
+        assert!(inputs.paths.is_empty());
+        assert!(inputs.stdin_trace);
+        let mut trace = String::new();
+        stdin.read_to_string(&mut trace).unwrap();
+        assert_eq!(trace, "{\"traceEvents\": []}");
+
+        // An empty stdin lists nothing
+        let inputs = collect_inputs(&["-".to_string()], &mut " \n".as_bytes()).unwrap();
+        assert!(inputs.paths.is_empty());
+        assert!(!inputs.stdin_trace);
+    }
+
+    #[test]
+    fn test_invalid_inputs() {
+        let directory = TestDir::new("invalid-inputs");
+        let missing = format!("@{}", directory.path().join("missing.rsp").display());
+        let error = collect_inputs(&[missing], &mut "".as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotFound);
+
+        let arguments = ["-".to_string(), "-".to_string()];
+        let error = collect_inputs(&arguments, &mut "a.json\n".as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    }
+}
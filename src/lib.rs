@@ -2,4 +2,17 @@ pub mod directory_walker;
 pub mod schema;
 pub mod trace_event;
 pub mod tracedb;
-pub mod file_parser;
\ No newline at end of file
+pub mod aggregate_config;
+pub mod build_graph;
+pub mod cargo_timings;
+pub mod compile_commands;
+pub mod file_parser;
+pub mod inputs;
+pub mod ninja_log;
+pub mod pipeline;
+pub mod provenance;
+pub mod report;
+pub mod sniff;
+pub mod time_report;
+#[cfg(test)]
+mod test_dir;
@@ -3,20 +3,39 @@ extern crate env_logger;
 extern crate libsqlite3_sys;
 extern crate timetracer;
 
-use argparse::{ArgumentParser, Store};
+use argparse::{ArgumentParser, List, Store, StoreFalse, StoreOption, StoreTrue};
 use diesel::RunQueryDsl;
-use log::{debug, warn};
-use timetracer::file_parser::json_parser;
+use log::{debug, info};
+use timetracer::aggregate_config::AggregateConfig;
+use timetracer::file_parser::ParseOptions;
+use timetracer::inputs::collect_inputs;
+use timetracer::pipeline::{default_jobs, ingest_reader, ingest_traces, retract_missing};
+use timetracer::report::ReportOptions;
 
 use std::env::current_dir;
+use std::io::{stdin, stdout};
 use std::path::PathBuf;
 
-use timetracer::directory_walker::iterate_json_files;
+use timetracer::directory_walker::{iterate_json_files, WalkOptions};
 
 fn main() {
     env_logger::init();
 
     let mut work_directory_string: String = current_dir().unwrap().to_str().unwrap().to_string();
+    let mut jobs: usize = default_jobs();
+    let mut rebuild = false;
+    let mut parse_options = ParseOptions::default();
+    let mut aggregates_string = String::new();
+    let mut inputs: Vec<String> = Vec::new();
+    let mut stdin_name = "stdin".to_string();
+    let mut roots: Vec<PathBuf> = Vec::new();
+    let mut walk_options = WalkOptions::default();
+    let mut compile_commands_string = String::new();
+    let mut reports: Vec<String> = Vec::new();
+    let mut ninja_log_string = String::new();
+    let mut report_options = ReportOptions::default();
+    let mut build_graph_string = String::new();
+    let mut cargo_timings_string = String::new();
     {
         let mut argparser = ArgumentParser::new();
         argparser.set_description("Analyze the Clang build time");
@@ -25,9 +44,120 @@ fn main() {
             Store,
             "Set the work directory, or the directory the project is built",
         );
+        argparser.refer(&mut jobs).add_option(
+            &["-j", "--jobs"],
+            Store,
+            "Number of threads parsing the traces, defaults to the number of CPUs",
+        );
+        argparser.refer(&mut rebuild).add_option(
+            &["--rebuild"],
+            StoreTrue,
+            "Discard the existing database content and ingest every trace again",
+        );
+        argparser.refer(&mut parse_options.raw_events).add_option(
+            &["--raw-events"],
+            StoreTrue,
+            "Also store every event of the traces in raw_events, the traces stored without them \
+            are parsed again",
+        );
+        argparser.refer(&mut parse_options.tolerant).add_option(
+            &["--tolerant"],
+            StoreTrue,
+            "Store the events of truncated traces up to the truncation, the objects are marked \
+            partial",
+        );
+        argparser.refer(&mut aggregates_string).add_option(
+            &["--aggregates"],
+            Store,
+            "JSON file mapping events to the aggregates table, the traces mapped with other \
+            rules are parsed again",
+        );
+        argparser.refer(&mut stdin_name).add_option(
+            &["--stdin-name"],
+            Store,
+            "Object name of a trace read from stdin, defaults to stdin",
+        );
+        argparser.refer(&mut roots).add_option(
+            &["--root"],
+            List,
+            "Directory to search the traces under, may be repeated, defaults to the work directory",
+        );
+        argparser.refer(&mut walk_options.include).add_option(
+            &["--include"],
+            List,
+            "Only ingest the traces matching this glob, may be repeated; a glob without / matches \
+            the file name, otherwise the path relative to the root",
+        );
+        argparser.refer(&mut walk_options.exclude).add_option(
+            &["--exclude"],
+            List,
+            "Skip the files and directories matching this glob, may be repeated, e.g. _deps",
+        );
+        argparser.refer(&mut walk_options.ignore_files).add_option(
+            &["--gitignore"],
+            StoreTrue,
+            "Skip what .gitignore and .ignore files exclude",
+        );
+        argparser.refer(&mut walk_options.max_depth).add_option(
+            &["--max-depth"],
+            StoreOption,
+            "Levels of directories to descend into below a root",
+        );
+        argparser.refer(&mut walk_options.follow_links).add_option(
+            &["--no-follow-symlinks"],
+            StoreFalse,
+            "Do not descend into symbolic links to directories",
+        );
+        argparser.refer(&mut compile_commands_string).add_option(
+            &["--compile-commands"],
+            Store,
+            "compile_commands.json to link the objects to their compile command, defaults to the \
+            one in the work directory if any",
+        );
+        argparser.refer(&mut ninja_log_string).add_option(
+            &["--ninja-log"],
+            Store,
+            ".ninja_log to import the build steps from, defaults to the one in the work directory \
+            if any",
+        );
+        argparser.refer(&mut cargo_timings_string).add_option(
+            &["--cargo-timings"],
+            Store,
+            "cargo-timing.html of cargo build --timings, or the output of --timings=json, to \
+            import the Rust units from, defaults to target/cargo-timings/cargo-timing.html in the \
+            work directory if any",
+        );
+        argparser.refer(&mut build_graph_string).add_option(
+            &["--build-graph"],
+            Store,
+            "build.ninja or the output of ninja -t graph for the critical-path report, defaults \
+            to the build.ninja of the work directory if any",
+        );
+        argparser.refer(&mut report_options.what_if).add_option(
+            &["--what-if"],
+            List,
+            "Scenario of the critical-path report as pattern:percent, the steps with an output \
+            matching the glob being that many percent faster, may be repeated",
+        );
+        argparser.refer(&mut reports).add_option(
+            &["--report"],
+            List,
+            "Print a report once the traces are ingested, may be repeated: flags, timeline, ninja, \
+            critical-path, build",
+        );
+        argparser.refer(&mut inputs).add_argument(
+            "inputs",
+            List,
+            "Traces to ingest instead of every trace under the work directory: paths, @file for a \
+            response file listing paths, or - for paths or a trace on stdin",
+        );
         argparser.parse_args_or_exit();
     }
     debug!("Work directory: {}", work_directory_string);
+    if !aggregates_string.is_empty() {
+        parse_options.aggregates =
+            AggregateConfig::load(&PathBuf::from(&aggregates_string)).unwrap();
+    }
 
     let database = PathBuf::from(&work_directory_string).join("tracedb.sqlite");
     let mut connection = timetracer::tracedb::get_connection(database.to_str().unwrap()).unwrap();
@@ -37,21 +167,103 @@ fn main() {
     diesel::sql_query("PRAGMA synchronous=OFF;").execute(&mut connection).unwrap();
     diesel::sql_query("PRAGMA locking_mode=exclusive;").execute(&mut connection).unwrap();
 
-    for path_result in iterate_json_files(&PathBuf::from(&work_directory_string)).unwrap() {
-        match path_result {
-            Err(e) => {
-                debug!("Error on {}", e);
-            }
-            Ok(path) => {
-                debug!("JSON file {}", path.display());
-                let result = json_parser(&path, &mut connection);
-                match result {
-                    Ok(()) => {}
-                    Err(e) => {
-                        warn!("Parse {} error {}", path.display(), e);
-                    }
+    if rebuild {
+        timetracer::tracedb::clear_tables(&mut connection).unwrap();
+    }
+    let removed = retract_missing(&mut connection).unwrap();
+
+    let mut paths = Vec::new();
+    let mut stdin = stdin().lock();
+    let mut stdin_trace = false;
+    if inputs.is_empty() {
+        if roots.is_empty() {
+            roots.push(PathBuf::from(&work_directory_string));
+        }
+        for path_result in iterate_json_files(&roots, &walk_options).unwrap() {
+            match path_result {
+                Err(e) => {
+                    debug!("Error on {}", e);
                 }
+                Ok(path) => paths.push(path),
             }
         }
+    } else {
+        let collected = collect_inputs(&inputs, &mut stdin).unwrap();
+        paths = collected.paths;
+        stdin_trace = collected.stdin_trace;
+    }
+    paths.sort();
+    paths.dedup();
+
+    let mut stats = ingest_traces(&paths, jobs, &parse_options, &mut connection).unwrap();
+    if stdin_trace {
+        ingest_reader(stdin, stdin_name, &paths, &parse_options, &mut stats, &mut connection)
+            .unwrap();
+    }
+    info!(
+        "{} traces parsed ({} partial, {} links), {} unchanged, {} failed, {} removed",
+        stats.parsed, stats.partial, stats.links, stats.unchanged, stats.failed, removed
+    );
+    for (reason, count) in &stats.skipped {
+        info!("{} JSON files skipped, {}", count, reason);
+    }
+    if stats.duplicates > 0 {
+        info!("{} traces skipped, another trace of their object was ingested", stats.duplicates);
+    }
+
+    let compile_commands = if compile_commands_string.is_empty() {
+        PathBuf::from(&work_directory_string).join("compile_commands.json")
+    } else {
+        PathBuf::from(&compile_commands_string)
+    };
+    if compile_commands.exists() {
+        let entries = timetracer::compile_commands::load(&compile_commands).unwrap();
+        let linked =
+            timetracer::compile_commands::link_compile_commands(&entries, &mut connection).unwrap();
+        info!("{} objects linked to {}", linked, compile_commands.display());
+    }
+
+    let ninja_log = if ninja_log_string.is_empty() {
+        PathBuf::from(&work_directory_string).join(".ninja_log")
+    } else {
+        PathBuf::from(&ninja_log_string)
+    };
+    if ninja_log.exists() {
+        let steps = timetracer::ninja_log::load(&ninja_log).unwrap();
+        let build_directory = ninja_log.parent().unwrap();
+        let linked =
+            timetracer::ninja_log::import_ninja_log(&steps, build_directory, &mut connection)
+                .unwrap();
+        info!("{} ninja steps imported, {} traced", steps.len(), linked);
+    }
+
+    let cargo_timings = if cargo_timings_string.is_empty() {
+        PathBuf::from(&work_directory_string).join("target/cargo-timings/cargo-timing.html")
+    } else {
+        PathBuf::from(&cargo_timings_string)
+    };
+    if cargo_timings.exists() {
+        let units = timetracer::cargo_timings::load(&cargo_timings).unwrap();
+        timetracer::cargo_timings::import_cargo_timings(&units, &mut connection).unwrap();
+        info!("{} cargo units imported", units.len());
+    }
+
+    let build_graph = if build_graph_string.is_empty() {
+        PathBuf::from(&work_directory_string).join("build.ninja")
+    } else {
+        PathBuf::from(&build_graph_string)
+    };
+    if build_graph.exists() {
+        report_options.build_graph = Some(build_graph);
+    }
+    report_options.jobs = jobs;
+    for report in &reports {
+        timetracer::report::write_report(
+            report,
+            &report_options,
+            &mut connection,
+            &mut stdout().lock(),
+        )
+        .unwrap();
     }
 }
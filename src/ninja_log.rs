@@ -0,0 +1,211 @@
+/// Import the build steps of `.ninja_log`, the last entry of every output
+extern crate log;
+
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use diesel::{prelude::*, SqliteConnection};
+use log::debug;
+
+use crate::{
+    compile_commands::normalize,
+    file_parser::INSERT_BATCH_SIZE,
+    schema::{links, ninja_steps, objects},
+    tracedb::NewNinjaStep,
+};
+
+/// One line of the log
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Milliseconds since ninja started
+    pub start: i64,
+    pub end: i64,
+    pub mtime: i64,
+    pub output: String,
+    pub command_hash: String,
+    /// Index of the build in the log, a step ending before the previous one starts a new build
+    pub build: i64,
+}
+
+/// Read the steps of the log, the last entry of every output in the order of the log
+pub fn parse<R: BufRead>(reader: R) -> Result<Vec<Entry>, Box<dyn Error + 'static>> {
+    let mut steps = Vec::<Entry>::new();
+    let mut by_output = HashMap::<String, usize>::new();
+    let mut build = 0;
+    let mut previous_end = None;
+    let mut built = HashSet::<String>::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if number == 0 {
+            match line.strip_prefix("# ninja log v") {
+                Some("5" | "6") => continue,
+                _ => return Err(format!("Unsupported ninja log version {}", line).into()),
+            }
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 5 {
+            return Err(format!("Malformed ninja log line {}: {}", number + 1, line).into());
+        }
+        let end: i64 = fields[1].parse()?;
+        let output = fields[3].to_string();
+        if previous_end.is_some_and(|previous_end| end < previous_end) || built.contains(&output) {
+            build += 1;
+            built.clear();
+        }
+        previous_end = Some(end);
+        built.insert(output.clone());
+        let step = Entry {
+            start: fields[0].parse()?,
+            end,
+            mtime: fields[2].parse()?,
+            output,
+            command_hash: fields[4].to_string(),
+            build,
+        };
+        match by_output.get(&step.output) {
+            Some(&index) => steps[index] = step,
+            None => {
+                by_output.insert(step.output.clone(), steps.len());
+                steps.push(step);
+            }
+        }
+    }
+    Ok(steps)
+}
+
+pub fn load(path: &Path) -> Result<Vec<Entry>, Box<dyn Error + 'static>> {
+    parse(BufReader::new(File::open(path)?))
+}
+
+/// Replace the content of `ninja_steps` with the steps, linked to the link or the object they
+/// build, returns how many were linked
+pub fn import_ninja_log(
+    steps: &[Entry],
+    build_directory: &Path,
+    conn: &mut SqliteConnection,
+) -> Result<usize, Box<dyn Error + 'static>> {
+    let objects: HashMap<_, String> = objects::table
+        .select(objects::path)
+        .load::<String>(conn)?
+        .into_iter()
+        .map(|object| (normalize(Path::new(&object)), object))
+        .collect();
+    let links: HashMap<_, String> = links::table
+        .select(links::path)
+        .load::<String>(conn)?
+        .into_iter()
+        .map(|link| (normalize(Path::new(&link)), link))
+        .collect();
+
+    let rows: Vec<NewNinjaStep> = steps
+        .iter()
+        .map(|step| {
+            let output = normalize(&build_directory.join(&step.output));
+            NewNinjaStep {
+                output: &step.output,
+                start_ms: step.start,
+                end_ms: step.end,
+                mtime: step.mtime,
+                command_hash: &step.command_hash,
+                object: links
+                    .get(&output)
+                    .or_else(|| objects.get(&output.with_extension("")))
+                    .map(String::as_str),
+                build: step.build,
+            }
+        })
+        .collect();
+    let linked = rows.iter().filter(|row| row.object.is_some()).count();
+    debug!("Import {} ninja steps, {} linked", rows.len(), linked);
+
+    conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+        diesel::delete(ninja_steps::table).execute(conn)?;
+        for batch in rows.chunks(INSERT_BATCH_SIZE) {
+            diesel::insert_into(ninja_steps::table)
+                .values(batch)
+                .execute(conn)?;
+        }
+        Ok(())
+    })?;
+    Ok(linked)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use diesel::prelude::*;
+
+    use crate::{
+        schema::{links, ninja_steps},
+        tracedb::{get_connection, NewLink, NewObject},
+    };
+
+    use super::{import_ninja_log, parse};
+
+    #[test]
+    fn test_import_ninja_log() {
+        let log = "# ninja log v5\n\
+            0\t1200\t1700000000\tobj/a.cpp.o\t1a2b\n\
+            10\t300\t1700000000\tgen/version.h\t3c4d\n\
+            5\t900\t1700000100\tobj/a.cpp.o\t5e6f\n\
+            1200\t1500\t1700000200\tapp\t7a8b\n\
+            900\t1100\t1700000200\tlibfoo.so\t9c0d\n";
+        let steps = parse(log.as_bytes()).unwrap();
+        assert_eq!(steps.len(), 4);
+        assert_eq!((steps[0].start, steps[0].end), (5, 900));
+        // gen/version.h ends before obj/a.cpp.o, libfoo.so before app
+        assert_eq!(
+            steps.iter().map(|step| step.build).collect::<Vec<_>>(),
+            vec![1, 1, 1, 2]
+        );
+        assert!(parse("# ninja log v4\n".as_bytes()).is_err());
+
+        let mut conn = get_connection(":memory:").unwrap();
+        NewObject::new("/build/obj/a.cpp", 0, 0, 0, false, None, None)
+            .insert(&mut conn)
+            .unwrap();
+        // The trace of a link is named after its output, extension included
+        for path in ["/build/app", "/build/libfoo.so"] {
+            diesel::insert_into(links::table)
+                .values(&NewLink {
+                    path,
+                    total_time: 250000,
+                    partial: false,
+                    start_time: None,
+                    end_time: None,
+                })
+                .execute(&mut conn)
+                .unwrap();
+        }
+        assert_eq!(
+            import_ninja_log(&steps, Path::new("/build"), &mut conn).unwrap(),
+            3
+        );
+        let rows: Vec<(String, Option<String>)> = ninja_steps::table
+            .select((ninja_steps::output, ninja_steps::object))
+            .order(ninja_steps::output)
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("app".to_string(), Some("/build/app".to_string())),
+                ("gen/version.h".to_string(), None),
+                (
+                    "libfoo.so".to_string(),
+                    Some("/build/libfoo.so".to_string())
+                ),
+                (
+                    "obj/a.cpp.o".to_string(),
+                    Some("/build/obj/a.cpp".to_string())
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,743 @@
+/// Parallel ingestion of traces
+extern crate log;
+
+use std::{
+    collections::{hash_map::Entry, BTreeMap, HashMap, HashSet},
+    error::Error,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::sync_channel,
+        Condvar, Mutex,
+    },
+    thread,
+};
+
+use diesel::{
+    result::{DatabaseErrorKind, Error as DieselError},
+    Connection, SqliteConnection,
+};
+use log::{debug, info, warn};
+
+use crate::{
+    file_parser::{
+        compression_extension, object_name, parse_trace, parse_trace_from, ParseOptions,
+        ParsedTrace,
+    },
+    provenance::{content_hash, FileStat, STATUS_ERROR, STATUS_OK},
+    sniff::{sniff_trace, SkipReason},
+    tracedb::{InsertTrait, NewTraceFile, TraceFile},
+};
+
+type BoxedError = Box<dyn Error + Send + Sync + 'static>;
+
+/// What a worker found out about one trace file
+enum Outcome {
+    /// Same size and modification time as in the previous run
+    Unchanged,
+    /// Modified on disk, but the content hash is the same as in the previous run
+    Touched { stat: FileStat, hash: String },
+    /// Not a trace, e.g. `compile_commands.json`
+    Skipped(SkipReason),
+    Parsed {
+        stat: FileStat,
+        hash: String,
+        parsed: Box<ParsedTrace>,
+    },
+    Failed {
+        fingerprint: Option<(FileStat, String)>,
+        error: BoxedError,
+    },
+}
+
+/// Counters reported at the end of an ingestion
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct IngestStats {
+    /// Traces parsed and stored in this run
+    pub parsed: usize,
+    /// Parsed traces which were truncated, only their events before the truncation were stored
+    pub partial: usize,
+    /// Parsed traces which are the ones of a linker, stored as links
+    pub links: usize,
+    /// Traces skipped because they did not change since the previous run
+    pub unchanged: usize,
+    /// Traces that could not be parsed or stored
+    pub failed: usize,
+    /// Traces left out because another trace of the same object was ingested
+    pub duplicates: usize,
+    /// JSON files that are not traces, by the reason they were skipped for
+    pub skipped: BTreeMap<SkipReason, usize>,
+}
+
+/// Number of worker threads used when none is specified
+pub fn default_jobs() -> usize {
+    thread::available_parallelism()
+        .map(|jobs| jobs.get())
+        .unwrap_or(1)
+}
+
+/// `aggregates` is the fingerprint of the aggregate rules of `options`
+fn inspect(
+    path: &Path,
+    previous: Option<&TraceFile>,
+    options: &ParseOptions,
+    aggregates: &str,
+) -> Outcome {
+    // Parse again a trace which failed or was stored with other options
+    let previous = previous.filter(|previous| {
+        previous.status != STATUS_ERROR
+            && (previous.raw_events || !options.raw_events)
+            && previous.aggregates == aggregates
+    });
+    let stat = match FileStat::of(path) {
+        Ok(stat) => stat,
+        Err(e) => {
+            return Outcome::Failed {
+                fingerprint: None,
+                error: Box::new(e),
+            }
+        }
+    };
+    if let Some(previous) = previous {
+        if previous.size == stat.size && previous.mtime == stat.mtime {
+            return Outcome::Unchanged;
+        }
+    }
+
+    match sniff_trace(path) {
+        Ok(None) => {}
+        Ok(Some(reason)) => return Outcome::Skipped(reason),
+        Err(e) => {
+            return Outcome::Failed {
+                fingerprint: None,
+                error: Box::new(e),
+            }
+        }
+    }
+
+    let hash = match content_hash(path) {
+        Ok(hash) => hash,
+        Err(e) => {
+            return Outcome::Failed {
+                fingerprint: None,
+                error: Box::new(e),
+            }
+        }
+    };
+    if let Some(previous) = previous {
+        if previous.hash == hash {
+            return Outcome::Touched { stat, hash };
+        }
+    }
+
+    debug!("JSON file {}", path.display());
+    match parse_trace(path, options) {
+        Ok(parsed) => Outcome::Parsed {
+            stat,
+            hash,
+            parsed: Box::new(parsed),
+        },
+        Err(error) => Outcome::Failed {
+            fingerprint: Some((stat, hash)),
+            error,
+        },
+    }
+}
+
+fn retract_previous(
+    previous: Option<&TraceFile>,
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error + 'static>> {
+    if let Some(previous) = previous.filter(|previous| previous.status == STATUS_OK) {
+        ParsedTrace::load(&previous.object, conn)?.retract(conn)?;
+    }
+    Ok(())
+}
+
+fn record(
+    path: &Path,
+    outcome: Outcome,
+    previous: Option<&TraceFile>,
+    options: &ParseOptions,
+    aggregates: &str,
+    stats: &mut IngestStats,
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error + 'static>> {
+    let path_str = path.to_str().expect("Failed to convert path to string");
+    match outcome {
+        Outcome::Unchanged => stats.unchanged += 1,
+        Outcome::Touched { stat, hash } => {
+            let previous = previous.unwrap();
+            NewTraceFile {
+                path: path_str,
+                object: &previous.object,
+                size: stat.size,
+                mtime: stat.mtime,
+                hash: &hash,
+                status: &previous.status,
+                raw_events: previous.raw_events,
+                aggregates: &previous.aggregates,
+            }
+            .insert(conn)?;
+            stats.unchanged += 1;
+        }
+        Outcome::Skipped(reason) => {
+            debug!("Skip {}, {}", path.display(), reason);
+            *stats.skipped.entry(reason).or_default() += 1;
+            if let Some(previous) = previous {
+                conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+                    retract_previous(Some(previous), conn)?;
+                    TraceFile::delete(&previous.path, conn)
+                })?;
+            }
+        }
+        Outcome::Parsed { stat, hash, parsed } => {
+            conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+                retract_previous(previous, conn)?;
+                parsed.persist(conn)?;
+                NewTraceFile {
+                    path: path_str,
+                    object: &parsed.object,
+                    size: stat.size,
+                    mtime: stat.mtime,
+                    hash: &hash,
+                    status: STATUS_OK,
+                    raw_events: options.raw_events,
+                    aggregates,
+                }
+                .insert(conn)
+            })?;
+            stats.parsed += 1;
+            if parsed.partial {
+                stats.partial += 1;
+            }
+            if parsed.is_link() {
+                stats.links += 1;
+            }
+        }
+        Outcome::Failed { fingerprint, error } => {
+            warn!("Parse {} error {}", path.display(), error);
+            stats.failed += 1;
+            if let Some((stat, hash)) = fingerprint {
+                conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+                    retract_previous(previous, conn)?;
+                    NewTraceFile {
+                        path: path_str,
+                        object: &object_name(path),
+                        size: stat.size,
+                        mtime: stat.mtime,
+                        hash: &hash,
+                        status: STATUS_ERROR,
+                        raw_events: false,
+                        aggregates,
+                    }
+                    .insert(conn)
+                })?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Split the traces into one per object, preferably the uncompressed one, and the others
+fn unique_objects(paths: &[PathBuf]) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let rank = |path: &Path| {
+        let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+        (compression_extension(path).is_none(), modified.ok())
+    };
+    let mut chosen = HashMap::<String, usize>::new();
+    for (index, path) in paths.iter().enumerate() {
+        match chosen.entry(object_name(path)) {
+            Entry::Vacant(entry) => {
+                entry.insert(index);
+            }
+            Entry::Occupied(mut entry) => {
+                if rank(path) > rank(&paths[*entry.get()]) {
+                    entry.insert(index);
+                }
+            }
+        }
+    }
+    let kept: HashSet<usize> = chosen.into_values().collect();
+    let (unique, duplicates): (Vec<_>, Vec<_>) = paths
+        .iter()
+        .enumerate()
+        .partition(|(index, _)| kept.contains(index));
+    let paths_of = |indexed: Vec<(usize, &PathBuf)>| -> Vec<PathBuf> {
+        indexed.into_iter().map(|(_, path)| path.clone()).collect()
+    };
+    (paths_of(unique), paths_of(duplicates))
+}
+
+/// Whether `record` only failed on a constraint, which rolls back the trace alone
+fn is_recoverable(error: &(dyn Error + 'static)) -> bool {
+    match error.downcast_ref::<DieselError>() {
+        Some(DieselError::DatabaseError(kind, _)) => matches!(
+            kind,
+            DatabaseErrorKind::UniqueViolation
+                | DatabaseErrorKind::ForeignKeyViolation
+                | DatabaseErrorKind::NotNullViolation
+                | DatabaseErrorKind::CheckViolation
+        ),
+        Some(_) => false,
+        None => true,
+    }
+}
+
+/// Retract the traces whose file no longer exists, returns how many
+pub fn retract_missing(conn: &mut SqliteConnection) -> Result<usize, Box<dyn Error + 'static>> {
+    let mut removed = 0;
+    for trace_file in TraceFile::load_all(conn)? {
+        if Path::new(&trace_file.path).exists() {
+            continue;
+        }
+        info!("Trace {} was removed", trace_file.path);
+        conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+            retract_previous(Some(&trace_file), conn)?;
+            TraceFile::delete(&trace_file.path, conn)
+        })?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+/// Parse and store a trace which is not read from a file of its own, e.g. from stdin, as
+/// `object`, unless `paths` holds a trace of it
+pub fn ingest_reader<R: Read>(
+    reader: R,
+    object: String,
+    paths: &[PathBuf],
+    options: &ParseOptions,
+    stats: &mut IngestStats,
+    conn: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error + 'static>> {
+    if let Some(path) = paths.iter().find(|path| object_name(path) == object) {
+        warn!(
+            "Skip the trace of {}, {} is ingested",
+            object,
+            path.display()
+        );
+        stats.duplicates += 1;
+        return Ok(());
+    }
+    let parsed = match parse_trace_from(reader, object, options) {
+        Ok(parsed) => parsed,
+        Err(error) => {
+            warn!("Parse stdin error {}", error);
+            stats.failed += 1;
+            return Ok(());
+        }
+    };
+    let stored = conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+        ParsedTrace::load(&parsed.object, conn)?.retract(conn)?;
+        parsed.persist(conn)
+    });
+    match stored {
+        Ok(()) => {
+            stats.parsed += 1;
+            if parsed.partial {
+                stats.partial += 1;
+            }
+            if parsed.is_link() {
+                stats.links += 1;
+            }
+        }
+        Err(error) if is_recoverable(error.as_ref()) => {
+            warn!("Store {} error {}", parsed.object, error);
+            stats.failed += 1;
+        }
+        Err(error) => return Err(error),
+    }
+    Ok(())
+}
+
+/// How many traces the workers may parse ahead of the writer
+struct Window {
+    size: usize,
+    /// Traces persisted so far, `usize::MAX` once the writer stopped
+    persisted: Mutex<usize>,
+    advanced: Condvar,
+}
+
+impl Window {
+    fn new(size: usize) -> Self {
+        Window {
+            size,
+            persisted: Mutex::new(0),
+            advanced: Condvar::new(),
+        }
+    }
+
+    /// Block until the trace at `index` may be parsed
+    fn wait_for(&self, index: usize) {
+        let mut persisted = self.persisted.lock().unwrap();
+        while index >= persisted.saturating_add(self.size) {
+            persisted = self.advanced.wait(persisted).unwrap();
+        }
+    }
+
+    fn advance(&self, persisted: usize) {
+        *self.persisted.lock().unwrap() = persisted;
+        self.advanced.notify_all();
+    }
+}
+
+/// Parse the traces on `jobs` worker threads and persist them through `conn`, in the order of
+/// `paths` so that the tables do not depend on the number of jobs. Unchanged traces are skipped.
+pub fn ingest_traces(
+    paths: &[PathBuf],
+    jobs: usize,
+    options: &ParseOptions,
+    conn: &mut SqliteConnection,
+) -> Result<IngestStats, Box<dyn Error + 'static>> {
+    let previous: HashMap<String, TraceFile> = TraceFile::load_all(conn)?
+        .into_iter()
+        .map(|trace_file| (trace_file.path.clone(), trace_file))
+        .collect();
+    let previous_of = |path: &Path| path.to_str().and_then(|path| previous.get(path));
+    let aggregates = &options.aggregates.fingerprint();
+
+    let (paths, duplicates) = unique_objects(paths);
+    for duplicate in &duplicates {
+        warn!(
+            "Skip {}, another trace of {} is ingested",
+            duplicate.display(),
+            object_name(duplicate)
+        );
+        if let Some(previous) = previous_of(duplicate) {
+            conn.transaction::<_, Box<dyn Error + 'static>, _>(|conn| {
+                retract_previous(Some(previous), conn)?;
+                TraceFile::delete(&previous.path, conn)
+            })?;
+        }
+    }
+    let paths = &paths[..];
+
+    let jobs = jobs.clamp(1, paths.len().max(1));
+    let next_path = AtomicUsize::new(0);
+    // Workers cannot run further ahead of the writer than the window, whichever trace is slow
+    let window = Window::new(jobs * 2);
+    let (sender, receiver) = sync_channel::<(usize, Outcome)>(jobs * 2);
+
+    debug!("Ingest {} traces with {} jobs", paths.len(), jobs);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            let sender = sender.clone();
+            let next_path = &next_path;
+            let previous_of = &previous_of;
+            let window = &window;
+            scope.spawn(move || loop {
+                let index = next_path.fetch_add(1, Ordering::Relaxed);
+                if index >= paths.len() {
+                    break;
+                }
+                window.wait_for(index);
+                let outcome = inspect(
+                    &paths[index],
+                    previous_of(&paths[index]),
+                    options,
+                    aggregates,
+                );
+                if sender.send((index, outcome)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+
+        let persist = || {
+            let mut pending = BTreeMap::<usize, Outcome>::new();
+            let mut next_to_persist = 0;
+            let mut stats = IngestStats {
+                duplicates: duplicates.len(),
+                ..Default::default()
+            };
+            for (index, outcome) in receiver {
+                pending.insert(index, outcome);
+                while let Some(outcome) = pending.remove(&next_to_persist) {
+                    let path = &paths[next_to_persist];
+                    if let Err(error) = record(
+                        path,
+                        outcome,
+                        previous_of(path),
+                        options,
+                        aggregates,
+                        &mut stats,
+                        conn,
+                    ) {
+                        if !is_recoverable(error.as_ref()) {
+                            return Err(error);
+                        }
+                        warn!("Store {} error {}", path.display(), error);
+                        stats.failed += 1;
+                    }
+                    next_to_persist += 1;
+                    window.advance(next_to_persist);
+                }
+            }
+            Ok(stats)
+        };
+        let result = persist();
+        // Release the workers waiting for their turn if the writer stopped early
+        window.advance(usize::MAX);
+        result
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use std::fs;
+
+    use diesel::prelude::*;
+
+    use crate::{
+        aggregate_config::{AggregateConfig, AggregateRule, KeyField},
+        file_parser::{object_name, ParseOptions},
+        schema::{aggregates, object_facts, raw_events, source},
+        sniff::SkipReason,
+        test_dir::TestDir,
+        tracedb::{get_connection, NewObject},
+    };
+
+    use super::{ingest_reader, ingest_traces, retract_missing};
+
+    #[test]
+    fn test_ingest_is_independent_of_jobs() {
+        let directory = TestDir::new("pipeline");
+
+        let mut paths = Vec::new();
+        for index in 0..8 {
+            let path = directory.write(
+                &format!("object{}.json", index),
+                format!(
+                    r#"{{"traceEvents": [
+                        {{"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": {}, "name": "Source", "args": {{"detail": "common.h"}}}},
+                        {{"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 100, "name": "Total Frontend"}}
+                    ]}}"#,
+                    index + 1
+                ),
+            );
+            paths.push(path);
+        }
+        // A broken trace in the middle fails alone, whatever the number of jobs
+        paths.insert(4, directory.write("broken.json", r#"{"traceEvents": [{"#));
+
+        let mut results = Vec::new();
+        for jobs in [1, 4] {
+            let mut conn = get_connection(":memory:").unwrap();
+            let stats = ingest_traces(&paths, jobs, &ParseOptions::default(), &mut conn).unwrap();
+            assert_eq!((stats.parsed, stats.failed), (8, 1));
+            let rows: Vec<(String, i32, i32)> = source::table
+                .select((source::path, source::duration, source::count))
+                .load(&mut conn)
+                .unwrap();
+            results.push(rows);
+        }
+
+        assert_eq!(results[0], vec![("common.h".to_string(), 36, 8)]);
+        assert_eq!(results[0], results[1]);
+    }
+
+    #[test]
+    fn test_incremental_ingest() {
+        let directory = TestDir::new("incremental");
+        let trace = |duration: i32| {
+            format!(
+                r#"{{"traceEvents": [
+                    {{"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": {}, "name": "Source", "args": {{"detail": "common.h"}}}}
+                ]}}"#,
+                duration
+            )
+        };
+        let paths = vec![
+            directory.write("a.json", trace(10)),
+            directory.write("b.json", trace(20)),
+        ];
+        let compile_commands = directory.write(
+            "compile_commands.json",
+            r#"[{"directory": "/build", "command": "clang++ -c a.cpp", "file": "a.cpp"}]"#,
+        );
+
+        let mut conn = get_connection(":memory:").unwrap();
+        let source_rows = |conn: &mut SqliteConnection| -> Vec<(String, i32, i32)> {
+            source::table
+                .select((source::path, source::duration, source::count))
+                .load(conn)
+                .unwrap()
+        };
+
+        let mut all_paths = paths.clone();
+        all_paths.push(compile_commands);
+        let stats = ingest_traces(&all_paths, 2, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!(stats.parsed, 2);
+        assert_eq!(stats.skipped[&SkipReason::NoTraceEvents], 1);
+        assert_eq!(
+            source_rows(&mut conn),
+            vec![("common.h".to_string(), 30, 2)]
+        );
+
+        let stats = ingest_traces(&paths, 2, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!(stats.unchanged, 2);
+        assert_eq!(
+            source_rows(&mut conn),
+            vec![("common.h".to_string(), 30, 2)]
+        );
+
+        fs::write(&paths[1], trace(200)).unwrap();
+        let stats = ingest_traces(&paths, 2, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!(stats.parsed, 1);
+        assert_eq!(
+            source_rows(&mut conn),
+            vec![("common.h".to_string(), 210, 2)]
+        );
+
+        fs::remove_file(&paths[0]).unwrap();
+        assert_eq!(retract_missing(&mut conn).unwrap(), 1);
+        assert_eq!(
+            source_rows(&mut conn),
+            vec![("common.h".to_string(), 200, 1)]
+        );
+        let facts: Vec<(String, i32)> = object_facts::table
+            .filter(object_facts::name.eq("common.h"))
+            .select((object_facts::object, object_facts::duration))
+            .load(&mut conn)
+            .unwrap();
+        assert_eq!(facts, vec![(object_name(&paths[1]), 200)]);
+
+        // A trace which no longer parses takes its previous contribution away
+        fs::write(&paths[1], r#"{"traceEvents": [{"#).unwrap();
+        let stats = ingest_traces(&paths[1..], 2, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!((stats.parsed, stats.failed), (0, 1));
+        assert_eq!(source_rows(&mut conn), vec![]);
+    }
+
+    #[test]
+    fn test_store_error_is_not_fatal() {
+        let directory = TestDir::new("store-error");
+        let trace = r#"{"traceEvents": [{"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "name": "Total Frontend"}]}"#;
+        let paths = vec![
+            directory.write("a.json", trace),
+            directory.write("b.json", trace),
+        ];
+
+        // The object of a.json already exists, storing it violates the primary key of objects
+        let mut conn = get_connection(":memory:").unwrap();
+        NewObject::new(&object_name(&paths[0]), 0, 0, 0, false, None, None)
+            .insert(&mut conn)
+            .unwrap();
+        let stats = ingest_traces(&paths, 1, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!((stats.parsed, stats.failed), (1, 1));
+    }
+
+    #[test]
+    fn test_options_parse_again() {
+        let directory = TestDir::new("options-again");
+        let paths = vec![directory.write(
+            "a.json",
+            r#"{"traceEvents": [{"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "name": "Source", "args": {"detail": "a.h"}}]}"#,
+        )];
+        let mut conn = get_connection(":memory:").unwrap();
+        ingest_traces(&paths, 1, &ParseOptions::default(), &mut conn).unwrap();
+
+        // The raw events were not stored the first time
+        let options = ParseOptions {
+            raw_events: true,
+            ..Default::default()
+        };
+        let stats = ingest_traces(&paths, 1, &options, &mut conn).unwrap();
+        assert_eq!(stats.parsed, 1);
+        assert_eq!(raw_events::table.count().get_result(&mut conn), Ok(1));
+        let stats = ingest_traces(&paths, 1, &options, &mut conn).unwrap();
+        assert_eq!(stats.unchanged, 1);
+        let stats = ingest_traces(&paths, 1, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!(stats.unchanged, 1);
+
+        // Nor were they mapped with these aggregate rules
+        let options = ParseOptions {
+            aggregates: AggregateConfig {
+                aggregates: vec![AggregateRule {
+                    aggregate: "headers".to_string(),
+                    name: Some("Source".to_string()),
+                    category: None,
+                    key: KeyField::default(),
+                }],
+            },
+            ..Default::default()
+        };
+        let stats = ingest_traces(&paths, 1, &options, &mut conn).unwrap();
+        assert_eq!(stats.parsed, 1);
+        assert_eq!(aggregates::table.count().get_result(&mut conn), Ok(1));
+        let stats = ingest_traces(&paths, 1, &options, &mut conn).unwrap();
+        assert_eq!(stats.unchanged, 1);
+    }
+
+    #[test]
+    fn test_failed_trace_is_parsed_again() {
+        let directory = TestDir::new("failed-again");
+        let path = directory.write(
+            "a.json",
+            r#"{"traceEvents": [
+                {"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "name": "Source", "args": {"detail": "a.h"}},
+                {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 5, "name": "Sou"#,
+        );
+        let paths = vec![path];
+
+        let mut conn = get_connection(":memory:").unwrap();
+        let stats = ingest_traces(&paths, 1, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!((stats.parsed, stats.failed), (0, 1));
+        let stats = ingest_traces(&paths, 1, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!((stats.unchanged, stats.failed), (0, 1));
+
+        let options = ParseOptions {
+            tolerant: true,
+            ..Default::default()
+        };
+        let stats = ingest_traces(&paths, 1, &options, &mut conn).unwrap();
+        assert_eq!((stats.parsed, stats.partial, stats.failed), (1, 1, 0));
+        let stats = ingest_traces(&paths, 1, &options, &mut conn).unwrap();
+        assert_eq!(stats.unchanged, 1);
+        assert_eq!(
+            source::table.select(source::count).first::<i32>(&mut conn),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn test_duplicate_objects() {
+        let directory = TestDir::new("duplicates");
+        let trace = r#"{"traceEvents": [{"pid": 1, "tid": 1, "ph": "X", "ts": 0, "dur": 10, "name": "Total Frontend"}]}"#;
+        let plain = directory.write("foo.cpp.json", trace);
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, trace.as_bytes()).unwrap();
+        let compressed = directory.write("foo.cpp.json.gz", encoder.finish().unwrap());
+
+        let mut conn = get_connection(":memory:").unwrap();
+        let paths = vec![plain.clone(), compressed];
+        let mut stats = ingest_traces(&paths, 2, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!((stats.parsed, stats.duplicates, stats.failed), (1, 1, 0));
+
+        // Neither a trace on stdin of the same object nor a broken one stops the ingest
+        for (stdin, object) in [(trace, object_name(&plain)), ("{", "stdin".to_string())] {
+            let options = ParseOptions::default();
+            ingest_reader(
+                stdin.as_bytes(),
+                object,
+                &paths,
+                &options,
+                &mut stats,
+                &mut conn,
+            )
+            .unwrap();
+        }
+        assert_eq!((stats.parsed, stats.duplicates, stats.failed), (1, 2, 1));
+
+        // The compressed copy is ingested once the plain trace is gone
+        fs::remove_file(&plain).unwrap();
+        retract_missing(&mut conn).unwrap();
+        let stats = ingest_traces(&paths[1..], 2, &ParseOptions::default(), &mut conn).unwrap();
+        assert_eq!((stats.parsed, stats.failed), (1, 0));
+    }
+}
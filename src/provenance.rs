@@ -0,0 +1,67 @@
+/// Track which trace files were ingested, so unchanged ones can be skipped on the next run
+use std::{
+    fs::{self, File},
+    io::{self, Read},
+    path::Path,
+    time::UNIX_EPOCH,
+};
+
+/// The trace was parsed and its contribution stored
+pub const STATUS_OK: &str = "ok";
+/// The trace could not be parsed, it is parsed again on the next run
+pub const STATUS_ERROR: &str = "error";
+
+/// Size and modification time of a file, cheap to compare with a previous run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStat {
+    pub size: i64,
+    /// Microseconds since the UNIX epoch
+    pub mtime: i64,
+}
+
+impl FileStat {
+    pub fn of(path: &Path) -> io::Result<FileStat> {
+        let metadata = fs::metadata(path)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_micros() as i64)
+            .unwrap_or(0);
+        Ok(FileStat {
+            size: metadata.len() as i64,
+            mtime,
+        })
+    }
+}
+
+const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+
+/// 64 bit FNV-1a
+fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    const PRIME: u64 = 0x100000001b3;
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(PRIME);
+    }
+    hash
+}
+
+/// Hash of the file content, as hex
+pub fn content_hash(path: &Path) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut hash = OFFSET_BASIS;
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hash = fnv1a(hash, &buffer[..read]);
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+/// Hash of the bytes, as hex
+pub fn bytes_hash(bytes: &[u8]) -> String {
+    format!("{:016x}", fnv1a(OFFSET_BASIS, bytes))
+}
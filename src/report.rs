@@ -0,0 +1,1303 @@
+/// Reports computed from the database once the traces are ingested
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    error::Error,
+    fmt,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use diesel::{prelude::*, SqliteConnection};
+
+use crate::{
+    build_graph::{critical_path, simulate, BuildGraph},
+    compile_commands::split_command,
+    schema::{cargo_units, compile_commands, links, ninja_steps, objects},
+};
+
+/// The reports `--report` accepts
+pub const REPORTS: [&str; 5] = ["flags", "timeline", "ninja", "critical-path", "build"];
+
+/// What the reports need besides the database
+#[derive(Debug, Default, Clone)]
+pub struct ReportOptions {
+    /// `build.ninja` or the output of `ninja -t graph`
+    pub build_graph: Option<PathBuf>,
+    /// Scenarios of the critical path report, as `pattern:percent` faster
+    pub what_if: Vec<String>,
+    /// Number of jobs the scenarios are simulated with
+    pub jobs: usize,
+}
+
+/// Write the report `name` to `out`
+pub fn write_report(
+    name: &str,
+    options: &ReportOptions,
+    conn: &mut SqliteConnection,
+    out: &mut dyn Write,
+) -> Result<(), Box<dyn Error + 'static>> {
+    match name {
+        "flags" => write!(out, "{}", FlagReport::load(conn)?)?,
+        "timeline" => write!(out, "{}", TimelineReport::load(conn)?)?,
+        "ninja" => write!(out, "{}", NinjaReport::load(conn)?)?,
+        "build" => write!(out, "{}", BuildReport::load(conn)?)?,
+        "critical-path" => {
+            let build_graph = options
+                .build_graph
+                .as_ref()
+                .ok_or("The critical-path report needs a build graph")?;
+            let report =
+                CriticalPathReport::load(build_graph, &options.what_if, options.jobs, conn)?;
+            write!(out, "{}", report)?
+        }
+        _ => {
+            return Err(format!(
+                "Unknown report {}, expected one of {}",
+                name,
+                REPORTS.join(", ")
+            )
+            .into())
+        }
+    }
+    Ok(())
+}
+
+/// Options followed by a path, which is left out of the flag
+const SKIPPED_WITH_VALUE: [&str; 8] = [
+    "-o",
+    "-I",
+    "-isystem",
+    "-iquote",
+    "-idirafter",
+    "-MF",
+    "-MT",
+    "-MQ",
+];
+
+/// Options followed by a separate value which is part of the flag
+const JOINED_WITH_VALUE: [&str; 6] = ["-Xclang", "-x", "-target", "-include", "-arch", "-mllvm"];
+
+/// The flags of a compile command which may change its compile time, without paths or
+/// `-ftime-trace`
+fn command_flags(command: &str) -> BTreeSet<String> {
+    let mut flags = BTreeSet::new();
+    let arguments = split_command(command);
+    let mut iter = arguments.iter();
+    while let Some(argument) = iter.next() {
+        if !argument.starts_with('-') {
+            continue;
+        }
+        if SKIPPED_WITH_VALUE.contains(&argument.as_str()) {
+            iter.next();
+        } else if JOINED_WITH_VALUE.contains(&argument.as_str()) {
+            match iter.next() {
+                Some(value) if argument == "-Xclang" && value.starts_with("-ftime-trace") => {}
+                Some(value) => {
+                    flags.insert(format!("{} {}", argument, value));
+                }
+                None => {}
+            }
+        } else if !(SKIPPED_WITH_VALUE
+            .iter()
+            .any(|option| argument.starts_with(option))
+            || ["-c", "-MD", "-MMD"].contains(&argument.as_str())
+            || argument.starts_with("-ftime-trace"))
+        {
+            flags.insert(argument.clone());
+        }
+    }
+    flags
+}
+
+/// What kind of setting a flag is
+fn flag_group(flag: &str) -> &'static str {
+    if flag.starts_with("-O") {
+        "optimization"
+    } else if flag.starts_with("-g") {
+        "debug"
+    } else if flag.starts_with("-fsanitize") || flag.starts_with("-fno-sanitize") {
+        "sanitizer"
+    } else if flag.starts_with("-D") || flag.starts_with("-U") {
+        "define"
+    } else if flag.contains("exceptions") || flag.contains("rtti") {
+        "exceptions/rtti"
+    } else if flag.starts_with("-std=") {
+        "standard"
+    } else if flag.starts_with("-W") || flag == "-w" || flag == "-pedantic" {
+        "warning"
+    } else {
+        "other"
+    }
+}
+
+/// Mean compile time of the objects built with and without a flag
+#[derive(Debug, PartialEq)]
+pub struct FlagImpact {
+    pub group: &'static str,
+    pub flag: String,
+    pub with: usize,
+    pub without: usize,
+    /// Mean frontend time of the objects built with the flag, in microseconds
+    pub frontend: f64,
+    /// Mean frontend time with the flag minus the mean without it
+    pub frontend_delta: f64,
+    pub backend: f64,
+    pub backend_delta: f64,
+}
+
+/// Mean compile time of the objects built with the same flags of a group
+#[derive(Debug, PartialEq)]
+pub struct GroupSetting {
+    /// The flags of the group, empty for the objects built with none of them
+    pub flags: String,
+    pub objects: usize,
+    pub frontend: f64,
+    pub backend: f64,
+}
+
+impl GroupSetting {
+    fn total(&self) -> f64 {
+        self.frontend + self.backend
+    }
+}
+
+/// How much the setting of a flag group changes the compile time
+#[derive(Debug, PartialEq)]
+pub struct GroupImpact {
+    pub group: &'static str,
+    /// The slowest first
+    pub settings: Vec<GroupSetting>,
+    /// Mean time of the slowest setting minus the one of the fastest
+    pub spread: f64,
+}
+
+/// Correlation of the compile flags with the compile time of the complete objects
+#[derive(Debug, Default)]
+pub struct FlagReport {
+    pub groups: Vec<GroupImpact>,
+    pub impacts: Vec<FlagImpact>,
+    /// Objects with a compile command the means are computed over
+    pub objects: usize,
+    /// Partial objects left out
+    pub partial: usize,
+}
+
+impl FlagReport {
+    pub fn load(conn: &mut SqliteConnection) -> Result<FlagReport, Box<dyn Error + 'static>> {
+        let times: HashMap<String, (i32, i32, bool)> = objects::table
+            .select((
+                objects::path,
+                objects::frontend,
+                objects::backend,
+                objects::partial,
+            ))
+            .load::<(String, i32, i32, bool)>(conn)?
+            .into_iter()
+            .map(|(path, frontend, backend, partial)| (path, (frontend, backend, partial)))
+            .collect();
+        let commands: Vec<(String, String)> = compile_commands::table
+            .select((compile_commands::object, compile_commands::command))
+            .load(conn)?;
+
+        let mut report = FlagReport::default();
+        let mut objects = Vec::new();
+        for (object, command) in commands {
+            match times.get(&object) {
+                Some((_, _, true)) => report.partial += 1,
+                Some((frontend, backend, false)) => {
+                    objects.push((command_flags(&command), *frontend as f64, *backend as f64))
+                }
+                None => {}
+            }
+        }
+        report.objects = objects.len();
+
+        let all_flags: BTreeSet<&String> = objects.iter().flat_map(|(flags, _, _)| flags).collect();
+        let groups: BTreeSet<&'static str> =
+            all_flags.iter().map(|flag| flag_group(flag)).collect();
+        for group in groups {
+            let mut sums = BTreeMap::<String, (usize, f64, f64)>::new();
+            for (flags, frontend, backend) in &objects {
+                let setting: Vec<&str> = flags
+                    .iter()
+                    .filter(|flag| flag_group(flag) == group)
+                    .map(String::as_str)
+                    .collect();
+                let sum = sums.entry(setting.join(" ")).or_default();
+                sum.0 += 1;
+                sum.1 += frontend;
+                sum.2 += backend;
+            }
+            if sums.len() < 2 {
+                continue;
+            }
+            let mut settings: Vec<GroupSetting> = sums
+                .into_iter()
+                .map(|(flags, (count, frontend, backend))| GroupSetting {
+                    flags,
+                    objects: count,
+                    frontend: frontend / count as f64,
+                    backend: backend / count as f64,
+                })
+                .collect();
+            settings.sort_by(|a, b| b.total().total_cmp(&a.total()));
+            let spread = settings[0].total() - settings[settings.len() - 1].total();
+            report.groups.push(GroupImpact {
+                group,
+                settings,
+                spread,
+            });
+        }
+        report.groups.sort_by(|a, b| b.spread.total_cmp(&a.spread));
+
+        for flag in all_flags {
+            let mut sums = [(0usize, 0f64, 0f64); 2];
+            for (flags, frontend, backend) in &objects {
+                let sum = &mut sums[flags.contains(flag) as usize];
+                sum.0 += 1;
+                sum.1 += frontend;
+                sum.2 += backend;
+            }
+            let [without, with] = sums;
+            if with.0 == 0 || without.0 == 0 {
+                continue;
+            }
+            let mean = |total: f64, count: usize| total / count as f64;
+            report.impacts.push(FlagImpact {
+                group: flag_group(flag),
+                flag: flag.clone(),
+                with: with.0,
+                without: without.0,
+                frontend: mean(with.1, with.0),
+                frontend_delta: mean(with.1, with.0) - mean(without.1, without.0),
+                backend: mean(with.2, with.0),
+                backend_delta: mean(with.2, with.0) - mean(without.2, without.0),
+            });
+        }
+        report.impacts.sort_by(|a, b| {
+            a.group.cmp(b.group).then(
+                (b.frontend_delta + b.backend_delta)
+                    .total_cmp(&(a.frontend_delta + a.backend_delta)),
+            )
+        });
+        Ok(report)
+    }
+}
+
+impl fmt::Display for FlagReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |us: f64| us / 1000.0;
+        writeln!(
+            f,
+            "Flag impact over {} objects with a compile command, {} partial objects left out",
+            self.objects, self.partial
+        )?;
+        writeln!(
+            f,
+            "{:<16} {:>10} {:<40} {:>7} {:>12} {:>12}",
+            "group", "spread ms", "flags", "objects", "frontend ms", "backend ms"
+        )?;
+        for group in &self.groups {
+            for (index, setting) in group.settings.iter().enumerate() {
+                let (name, spread) = match index {
+                    0 => (group.group, format!("{:.1}", ms(group.spread))),
+                    _ => ("", String::new()),
+                };
+                let flags = match setting.flags.as_str() {
+                    "" => "(none)",
+                    flags => flags,
+                };
+                writeln!(
+                    f,
+                    "{:<16} {:>10} {:<40} {:>7} {:>12.1} {:>12.1}",
+                    name,
+                    spread,
+                    flags,
+                    setting.objects,
+                    ms(setting.frontend),
+                    ms(setting.backend)
+                )?;
+            }
+        }
+        writeln!(
+            f,
+            "\n{:<16} {:<40} {:>6} {:>6} {:>12} {:>10} {:>12} {:>10}",
+            "group", "flag", "with", "w/o", "frontend ms", "delta", "backend ms", "delta"
+        )?;
+        for impact in &self.impacts {
+            writeln!(
+                f,
+                "{:<16} {:<40} {:>6} {:>6} {:>12.1} {:>+10.1} {:>12.1} {:>+10.1}",
+                impact.group,
+                impact.flag,
+                impact.with,
+                impact.without,
+                ms(impact.frontend),
+                ms(impact.frontend_delta),
+                ms(impact.backend),
+                ms(impact.backend_delta)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Number of slices the build is cut into to show the concurrency over time
+const TIMELINE_SLICES: usize = 20;
+/// Number of idle gaps listed, the longest first
+const TIMELINE_GAPS: usize = 10;
+
+/// When the objects were compiled and linked over the whole build, the ones without a start time
+/// are left out
+#[derive(Debug, Default)]
+pub struct TimelineReport {
+    pub objects: usize,
+    pub partial: usize,
+    /// Links among the objects, and the sum of their time
+    pub links: usize,
+    pub link_time: i64,
+    /// Start of the first object and end of the last one, in microseconds since the UNIX epoch
+    pub start: i64,
+    pub end: i64,
+    /// Sum of the time of every object
+    pub busy: i64,
+    /// Most objects compiled at the same time
+    pub peak: usize,
+    /// Time spent with each number of objects compiled at the same time
+    pub concurrency: BTreeMap<usize, i64>,
+    /// Mean number of objects compiled at the same time, per slice of the build
+    pub slices: Vec<f64>,
+    /// Periods nothing was compiled in, as (offset from the start, duration), the longest first
+    pub gaps: Vec<(i64, i64)>,
+}
+
+impl TimelineReport {
+    pub fn load(conn: &mut SqliteConnection) -> Result<TimelineReport, Box<dyn Error + 'static>> {
+        let mut rows: Vec<(Option<i64>, Option<i64>, bool)> = objects::table
+            .select((objects::start_time, objects::end_time, objects::partial))
+            .load(conn)?;
+        let objects = rows.len();
+        rows.extend(
+            links::table
+                .select((links::start_time, links::end_time, links::partial))
+                .load::<(Option<i64>, Option<i64>, bool)>(conn)?,
+        );
+        let intervals: Vec<(i64, i64)> = rows
+            .iter()
+            .filter_map(|(start, end, _)| Some(((*start)?, (*end)?)))
+            .collect();
+        let link_intervals: Vec<(i64, i64)> = rows[objects..]
+            .iter()
+            .filter_map(|(start, end, _)| Some(((*start)?, (*end)?)))
+            .collect();
+        let mut report = TimelineReport::new(
+            &intervals,
+            rows.iter()
+                .filter(|(start, end, partial)| *partial && start.is_some() && end.is_some())
+                .count(),
+        );
+        report.links = link_intervals.len();
+        report.link_time = link_intervals.iter().map(|(start, end)| end - start).sum();
+        Ok(report)
+    }
+
+    fn new(intervals: &[(i64, i64)], partial: usize) -> TimelineReport {
+        let mut report = TimelineReport {
+            objects: intervals.len(),
+            partial,
+            ..Default::default()
+        };
+        if intervals.is_empty() {
+            return report;
+        }
+        report.start = intervals.iter().map(|(start, _)| *start).min().unwrap();
+        report.end = intervals.iter().map(|(_, end)| *end).max().unwrap();
+        report.busy = intervals.iter().map(|(start, end)| end - start).sum();
+
+        // Ends sort before starts at the same time, objects run back to back do not overlap
+        let mut changes: Vec<(i64, i32)> = intervals
+            .iter()
+            .flat_map(|(start, end)| [(*start, 1), (*end, -1)])
+            .collect();
+        changes.sort();
+        let mut level = 0usize;
+        let mut previous = report.start;
+        for (time, change) in changes {
+            if time > previous {
+                *report.concurrency.entry(level).or_default() += time - previous;
+                if level == 0 {
+                    report.gaps.push((previous - report.start, time - previous));
+                }
+                previous = time;
+            }
+            level = (level as i64 + change as i64) as usize;
+            report.peak = report.peak.max(level);
+        }
+        report
+            .gaps
+            .sort_by_key(|(offset, duration)| (-duration, *offset));
+
+        let wall = report.end - report.start;
+        if wall > 0 {
+            let slice = wall as f64 / TIMELINE_SLICES as f64;
+            report.slices = vec![0.0; TIMELINE_SLICES];
+            for (index, mean) in report.slices.iter_mut().enumerate() {
+                let from = report.start as f64 + slice * index as f64;
+                let to = from + slice;
+                let overlap: f64 = intervals
+                    .iter()
+                    .map(|(start, end)| {
+                        ((*end as f64).min(to) - (*start as f64).max(from)).max(0.0)
+                    })
+                    .sum();
+                *mean = overlap / slice;
+            }
+        }
+        report
+    }
+
+    /// Wall time of the build
+    pub fn wall(&self) -> i64 {
+        self.end - self.start
+    }
+
+    /// Parallel efficiency, the mean number of objects compiled at the same time
+    pub fn efficiency(&self) -> f64 {
+        if self.wall() == 0 {
+            return 0.0;
+        }
+        (self.busy - self.link_time) as f64 / self.wall() as f64
+    }
+
+    /// Parallel efficiency over the peak concurrency
+    pub fn peak_utilization(&self) -> f64 {
+        if self.peak == 0 {
+            return 0.0;
+        }
+        self.efficiency() / self.peak as f64
+    }
+}
+
+impl fmt::Display for TimelineReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = |us: i64| us as f64 / 1_000_000.0;
+        writeln!(
+            f,
+            "Timeline of {} objects with a start time, {} of them links, {} partial objects end at \
+            their truncation",
+            self.objects, self.links, self.partial
+        )?;
+        if self.objects == 0 {
+            return Ok(());
+        }
+        writeln!(
+            f,
+            "Wall time {:.2} s, compile time {:.2} s, link time {:.2} s, parallel efficiency {:.2} \
+            (compile time / wall time), peak {}, {:.1}% of the peak on average",
+            s(self.wall()),
+            s(self.busy - self.link_time),
+            s(self.link_time),
+            self.efficiency(),
+            self.peak,
+            self.peak_utilization() * 100.0
+        )?;
+
+        writeln!(f, "\n{:>11} {:>10} {:>7}", "concurrency", "time s", "share")?;
+        for (level, duration) in &self.concurrency {
+            writeln!(
+                f,
+                "{:>11} {:>10.2} {:>6.1}%",
+                level,
+                s(*duration),
+                *duration as f64 * 100.0 / self.wall() as f64
+            )?;
+        }
+
+        if !self.slices.is_empty() {
+            writeln!(f, "\n{:>10} {:>11}", "from s", "concurrency")?;
+            let slice = self.wall() as f64 / self.slices.len() as f64;
+            for (index, mean) in self.slices.iter().enumerate() {
+                writeln!(
+                    f,
+                    "{:>10.2} {:>11.2} {}",
+                    slice * index as f64 / 1_000_000.0,
+                    mean,
+                    "#".repeat(mean.round() as usize)
+                )?;
+            }
+        }
+
+        if !self.gaps.is_empty() {
+            writeln!(f, "\n{:>10} {:>10}", "idle at s", "for s")?;
+            for (offset, duration) in self.gaps.iter().take(TIMELINE_GAPS) {
+                writeln!(f, "{:>10.2} {:>10.2}", s(*offset), s(*duration))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of steps listed per table of the ninja report
+const NINJA_STEPS: usize = 10;
+
+/// A ninja step building a traced object, times in microseconds
+#[derive(Debug, PartialEq, Eq)]
+pub struct TracedStep {
+    pub object: String,
+    /// Time ninja measured for the step
+    pub ninja: i64,
+    /// `Total Frontend` plus `Total Backend` of the trace, or the time of the link
+    pub compile: i64,
+    /// The step is a link, traced by the linker
+    pub link: bool,
+    /// Time between ninja starting the step and the compiler starting to trace, relative to the
+    /// quickest step of the build
+    pub launch_delay: Option<i64>,
+}
+
+impl TracedStep {
+    /// Time of the step spent outside of the compilation
+    pub fn overhead(&self) -> i64 {
+        self.ninja - self.compile
+    }
+}
+
+/// Cross check of `.ninja_log` with the traces
+#[derive(Debug, Default)]
+pub struct NinjaReport {
+    /// Steps with no trace, as (output, duration in microseconds), the longest first
+    pub untraced: Vec<(String, i64)>,
+    /// Traced steps, the largest overhead first
+    pub traced: Vec<TracedStep>,
+    pub partial: usize,
+}
+
+impl NinjaReport {
+    pub fn load(conn: &mut SqliteConnection) -> Result<NinjaReport, Box<dyn Error + 'static>> {
+        let mut objects: HashMap<String, (i32, Option<i64>, bool, bool)> = objects::table
+            .select((
+                objects::path,
+                objects::total_time,
+                objects::start_time,
+                objects::partial,
+            ))
+            .load::<(String, i32, Option<i64>, bool)>(conn)?
+            .into_iter()
+            .map(|(path, total_time, start_time, partial)| {
+                (path, (total_time, start_time, partial, false))
+            })
+            .collect();
+        objects.extend(
+            links::table
+                .select((
+                    links::path,
+                    links::total_time,
+                    links::start_time,
+                    links::partial,
+                ))
+                .load::<(String, i32, Option<i64>, bool)>(conn)?
+                .into_iter()
+                .map(|(path, total_time, start_time, partial)| {
+                    (path, (total_time, start_time, partial, true))
+                }),
+        );
+        let steps: Vec<(String, i64, i64, Option<String>, i64)> = ninja_steps::table
+            .select((
+                ninja_steps::output,
+                ninja_steps::start_ms,
+                ninja_steps::end_ms,
+                ninja_steps::object,
+                ninja_steps::build,
+            ))
+            .load(conn)?;
+
+        let mut report = NinjaReport::default();
+        // When the compiler started tracing, relative to when ninja started the step
+        let mut launches = Vec::new();
+        for (output, start_ms, end_ms, object, build) in steps {
+            let ninja = (end_ms - start_ms) * 1000;
+            let Some((object, (compile, start_time, partial, link))) = object.and_then(|object| {
+                let times = *objects.get(&object)?;
+                Some((object, times))
+            }) else {
+                report.untraced.push((output, ninja));
+                continue;
+            };
+            if partial {
+                report.partial += 1;
+                continue;
+            }
+            launches.push(start_time.map(|start_time| (build, start_time - start_ms * 1000)));
+            report.traced.push(TracedStep {
+                object,
+                ninja,
+                compile: compile as i64,
+                link,
+                launch_delay: None,
+            });
+        }
+        // The clock of ninja and the one of the traces only differ by when the build started,
+        // estimated per build of the log from the step whose compiler started the quickest
+        let mut epochs = HashMap::<i64, i64>::new();
+        for (build, launch) in launches.iter().flatten() {
+            let epoch = epochs.entry(*build).or_insert(*launch);
+            *epoch = (*epoch).min(*launch);
+        }
+        for (step, launch) in report.traced.iter_mut().zip(launches) {
+            step.launch_delay = launch.map(|(build, launch)| launch - epochs[&build]);
+        }
+
+        report
+            .untraced
+            .sort_by(|(a_output, a), (b_output, b)| b.cmp(a).then(a_output.cmp(b_output)));
+        report.traced.sort_by(|a, b| {
+            b.overhead()
+                .cmp(&a.overhead())
+                .then(a.object.cmp(&b.object))
+        });
+        Ok(report)
+    }
+}
+
+impl fmt::Display for NinjaReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ms = |us: i64| us as f64 / 1000.0;
+        let untraced: i64 = self.untraced.iter().map(|(_, duration)| duration).sum();
+        writeln!(
+            f,
+            "{} steps without trace, {:.1} ms",
+            self.untraced.len(),
+            ms(untraced)
+        )?;
+        for (output, duration) in self.untraced.iter().take(NINJA_STEPS) {
+            writeln!(f, "{:>12.1} {}", ms(*duration), output)?;
+        }
+
+        let ninja: i64 = self.traced.iter().map(|step| step.ninja).sum();
+        let compile: i64 = self.traced.iter().map(|step| step.compile).sum();
+        let link: i64 = self
+            .traced
+            .iter()
+            .filter(|step| step.link)
+            .map(|step| step.compile)
+            .sum();
+        writeln!(
+            f,
+            "\n{} traced steps, {:.1} ms in ninja, {:.1} ms compiling, {:.1} ms linking, {:.1} ms \
+            overhead, {} partial objects left out",
+            self.traced.len(),
+            ms(ninja),
+            ms(compile - link),
+            ms(link),
+            ms(ninja - compile),
+            self.partial
+        )?;
+        writeln!(
+            f,
+            "{:>12} {:>12} {:>12} {:>12} object",
+            "ninja ms", "compile ms", "overhead ms", "launch ms"
+        )?;
+        for step in self.traced.iter().take(NINJA_STEPS) {
+            let launch = step
+                .launch_delay
+                .map_or("-".to_string(), |delay| format!("{:.1}", ms(delay)));
+            writeln!(
+                f,
+                "{:>12.1} {:>12.1} {:>12.1} {:>12} {}",
+                ms(step.ninja),
+                ms(step.compile),
+                ms(step.overhead()),
+                launch,
+                step.object
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Most jobs the build is simulated with
+const MAX_SIMULATED_JOBS: usize = 64;
+
+/// A "what if these steps were faster" scenario
+#[derive(Debug, PartialEq)]
+pub struct WhatIf {
+    pub pattern: String,
+    pub percent: f64,
+    /// Steps with an output matching the pattern
+    pub steps: usize,
+    pub critical_path: i64,
+    /// Simulated wall time with the jobs of the report
+    pub wall: i64,
+}
+
+/// What bounds the wall time of the build, from the build graph and the time of its steps
+#[derive(Debug, Default)]
+pub struct CriticalPathReport {
+    pub steps: usize,
+    /// Sum of the time of every step, in microseconds
+    pub total: i64,
+    pub critical_path: i64,
+    /// The steps of the critical path, as (first output, duration), in build order
+    pub path: Vec<(String, i64)>,
+    /// Simulated wall time per number of jobs
+    pub simulations: BTreeMap<usize, i64>,
+    pub jobs: usize,
+    pub what_ifs: Vec<WhatIf>,
+}
+
+impl CriticalPathReport {
+    pub fn load(
+        build_graph: &Path,
+        what_ifs: &[String],
+        jobs: usize,
+        conn: &mut SqliteConnection,
+    ) -> Result<CriticalPathReport, Box<dyn Error + 'static>> {
+        let graph = BuildGraph::load(build_graph)?;
+        let directory = build_graph.parent().unwrap_or(Path::new(""));
+        let durations = graph.durations(directory, conn)?;
+        let dependencies = graph.dependencies();
+
+        let cycle_error = |cycle| graph.cycle_error(&cycle);
+        let (length, path) = critical_path(&durations, &dependencies).map_err(cycle_error)?;
+        let mut report = CriticalPathReport {
+            steps: durations.len(),
+            total: durations.iter().sum(),
+            critical_path: length,
+            path: path
+                .into_iter()
+                .map(|step| (graph.steps[step].outputs[0].clone(), durations[step]))
+                .collect(),
+            jobs,
+            ..Default::default()
+        };
+        let mut simulated_jobs: Vec<usize> = std::iter::successors(Some(1usize), |jobs| {
+            Some(jobs * 2).filter(|jobs| *jobs <= MAX_SIMULATED_JOBS)
+        })
+        .collect();
+        simulated_jobs.push(jobs);
+        for simulated in simulated_jobs {
+            let wall = simulate(&durations, &dependencies, simulated).map_err(cycle_error)?;
+            report.simulations.insert(simulated, wall);
+        }
+
+        for what_if in what_ifs {
+            let (pattern, percent) = what_if
+                .rsplit_once(':')
+                .and_then(|(pattern, percent)| Some((pattern, percent.parse::<f64>().ok()?)))
+                .ok_or_else(|| format!("Invalid scenario {}, expected pattern:percent", what_if))?;
+            let matcher = globset::Glob::new(pattern)?.compile_matcher();
+            let mut steps = 0;
+            let faster: Vec<i64> = graph
+                .steps
+                .iter()
+                .zip(&durations)
+                .map(|(step, duration)| {
+                    if step.outputs.iter().any(|output| matcher.is_match(output)) {
+                        steps += 1;
+                        (*duration as f64 * (1.0 - percent / 100.0)).round() as i64
+                    } else {
+                        *duration
+                    }
+                })
+                .collect();
+            report.what_ifs.push(WhatIf {
+                pattern: pattern.to_string(),
+                percent,
+                steps,
+                critical_path: critical_path(&faster, &dependencies)
+                    .map_err(cycle_error)?
+                    .0,
+                wall: simulate(&faster, &dependencies, jobs).map_err(cycle_error)?,
+            });
+        }
+        Ok(report)
+    }
+}
+
+impl fmt::Display for CriticalPathReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = |us: i64| us as f64 / 1_000_000.0;
+        writeln!(
+            f,
+            "{} steps, {:.2} s of step time, critical path {:.2} s over {} steps",
+            self.steps,
+            s(self.total),
+            s(self.critical_path),
+            self.path.len()
+        )?;
+        for (output, duration) in &self.path {
+            writeln!(f, "{:>10.2} {}", s(*duration), output)?;
+        }
+
+        writeln!(f, "\n{:>6} {:>10} {:>10}", "jobs", "wall s", "speedup")?;
+        let serial = self.simulations.get(&1).copied().unwrap_or_default();
+        for (jobs, wall) in &self.simulations {
+            writeln!(
+                f,
+                "{:>6} {:>10.2} {:>9.2}x",
+                jobs,
+                s(*wall),
+                if *wall > 0 {
+                    serial as f64 / *wall as f64
+                } else {
+                    0.0
+                }
+            )?;
+        }
+
+        if !self.what_ifs.is_empty() {
+            let wall = self
+                .simulations
+                .get(&self.jobs)
+                .copied()
+                .unwrap_or_default();
+            writeln!(
+                f,
+                "\n{:<40} {:>8} {:>6} {:>16} {:>12} {:>10}",
+                "what if",
+                "faster",
+                "steps",
+                "critical path s",
+                format!("wall -j{} s", self.jobs),
+                "saved s"
+            )?;
+            for what_if in &self.what_ifs {
+                writeln!(
+                    f,
+                    "{:<40} {:>7}% {:>6} {:>16.2} {:>12.2} {:>10.2}",
+                    what_if.pattern,
+                    what_if.percent,
+                    what_if.steps,
+                    s(what_if.critical_path),
+                    s(what_if.wall),
+                    s(wall - what_if.wall)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of cargo units listed in the build report
+const BUILD_UNITS: usize = 20;
+
+/// A unit of cargo in the build report, times in microseconds
+#[derive(Debug, PartialEq)]
+pub struct BuildUnit {
+    /// Name and version of the crate, with the target and the mode unless it is the library built
+    pub unit: String,
+    pub duration: i64,
+    /// Time up to the metadata, the rest being the codegen
+    pub rmeta_time: Option<i64>,
+}
+
+/// Time of the C++ half of the build from the traces next to the Rust half from cargo
+#[derive(Debug, Default)]
+pub struct BuildReport {
+    pub objects: usize,
+    pub frontend: i64,
+    pub backend: i64,
+    pub links: usize,
+    pub link_time: i64,
+    pub units: usize,
+    pub rust_time: i64,
+    pub rust_frontend: i64,
+    pub rust_codegen: i64,
+    /// The longest units first
+    pub top_units: Vec<BuildUnit>,
+}
+
+impl BuildReport {
+    pub fn load(conn: &mut SqliteConnection) -> Result<BuildReport, Box<dyn Error + 'static>> {
+        let mut report = BuildReport::default();
+        for (frontend, backend) in objects::table
+            .select((objects::frontend, objects::backend))
+            .load::<(i32, i32)>(conn)?
+        {
+            report.objects += 1;
+            report.frontend += frontend as i64;
+            report.backend += backend as i64;
+        }
+        for total_time in links::table.select(links::total_time).load::<i32>(conn)? {
+            report.links += 1;
+            report.link_time += total_time as i64;
+        }
+
+        let units: Vec<(String, String, String, String, i64, Option<i64>)> = cargo_units::table
+            .select((
+                cargo_units::name,
+                cargo_units::version,
+                cargo_units::target,
+                cargo_units::mode,
+                cargo_units::duration,
+                cargo_units::rmeta_time,
+            ))
+            .load(conn)?;
+        for (name, version, target, mode, duration, rmeta_time) in units {
+            report.units += 1;
+            report.rust_time += duration;
+            if let Some(rmeta_time) = rmeta_time {
+                report.rust_frontend += rmeta_time;
+                report.rust_codegen += duration - rmeta_time;
+            }
+            let mut unit = format!("{} v{}", name, version);
+            if !target.is_empty() {
+                unit += &format!(" {}", target);
+            }
+            if mode != "build" {
+                unit += &format!(" ({})", mode);
+            }
+            report.top_units.push(BuildUnit {
+                unit,
+                duration,
+                rmeta_time,
+            });
+        }
+        report
+            .top_units
+            .sort_by(|a, b| b.duration.cmp(&a.duration).then(a.unit.cmp(&b.unit)));
+        report.top_units.truncate(BUILD_UNITS);
+        Ok(report)
+    }
+
+    /// Time of the C++ half: compiling and linking
+    pub fn cpp_time(&self) -> i64 {
+        self.frontend + self.backend + self.link_time
+    }
+}
+
+impl fmt::Display for BuildReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = |us: i64| us as f64 / 1_000_000.0;
+        writeln!(
+            f,
+            "C++: {} objects, {:.2} s compiling ({:.2} s frontend, {:.2} s backend), {} links, \
+            {:.2} s linking",
+            self.objects,
+            s(self.frontend + self.backend),
+            s(self.frontend),
+            s(self.backend),
+            self.links,
+            s(self.link_time)
+        )?;
+        writeln!(
+            f,
+            "Rust: {} units, {:.2} s ({:.2} s to metadata, {:.2} s codegen)",
+            self.units,
+            s(self.rust_time),
+            s(self.rust_frontend),
+            s(self.rust_codegen)
+        )?;
+        let total = self.cpp_time() + self.rust_time;
+        if total > 0 {
+            writeln!(
+                f,
+                "C++ {:.1}%, Rust {:.1}% of {:.2} s",
+                self.cpp_time() as f64 * 100.0 / total as f64,
+                self.rust_time as f64 * 100.0 / total as f64,
+                s(total)
+            )?;
+        }
+        if self.top_units.is_empty() {
+            return Ok(());
+        }
+
+        writeln!(
+            f,
+            "\n{:>10} {:>10} {:>10} unit",
+            "time s", "metadata s", "codegen s"
+        )?;
+        for unit in &self.top_units {
+            let (rmeta, codegen) = match unit.rmeta_time {
+                Some(rmeta_time) => (
+                    format!("{:.2}", s(rmeta_time)),
+                    format!("{:.2}", s(unit.duration - rmeta_time)),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            writeln!(
+                f,
+                "{:>10.2} {:>10} {:>10} {}",
+                s(unit.duration),
+                rmeta,
+                codegen,
+                unit.unit
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use diesel::prelude::*;
+
+    use crate::{
+        cargo_timings::{self, import_cargo_timings},
+        ninja_log::{import_ninja_log, parse},
+        schema::{compile_commands, links},
+        test_dir::TestDir,
+        tracedb::{get_connection, NewCompileCommand, NewLink, NewObject},
+    };
+
+    use super::{
+        command_flags, BuildReport, BuildUnit, CriticalPathReport, FlagReport, NinjaReport,
+        TimelineReport,
+    };
+
+    #[test]
+    fn test_flag_report() {
+        assert_eq!(
+            command_flags("ccache clang++ -O2 -I inc -Iinc2 -DNDEBUG -Xclang -ftime-trace -ftime-trace -c a.cpp -o a.o")
+                .into_iter()
+                .collect::<Vec<_>>(),
+            vec!["-DNDEBUG", "-O2"]
+        );
+
+        let mut conn = get_connection(":memory:").unwrap();
+        for (object, frontend, backend, partial, command) in [
+            ("a", 100, 1000, false, "clang++ -O2 -g -c a.cpp"),
+            ("b", 300, 3000, false, "clang++ -O2 -c b.cpp"),
+            ("c", 100, 100, false, "clang++ -O0 -g -c c.cpp"),
+            ("d", 9000, 9000, true, "clang++ -O0 -c d.cpp"),
+        ] {
+            NewObject::new(
+                object,
+                frontend + backend,
+                frontend,
+                backend,
+                partial,
+                None,
+                None,
+            )
+            .insert(&mut conn)
+            .unwrap();
+            diesel::insert_into(compile_commands::table)
+                .values(&NewCompileCommand {
+                    object,
+                    source: "",
+                    output: "",
+                    directory: "",
+                    command,
+                })
+                .execute(&mut conn)
+                .unwrap();
+        }
+
+        let report = FlagReport::load(&mut conn).unwrap();
+        assert_eq!((report.objects, report.partial), (3, 1));
+        let impact = |flag: &str| {
+            let impact = report
+                .impacts
+                .iter()
+                .find(|impact| impact.flag == flag)
+                .unwrap();
+            (impact.with, impact.frontend_delta, impact.backend_delta)
+        };
+        assert_eq!(impact("-O2"), (2, 100.0, 1900.0));
+        assert_eq!(impact("-g"), (2, -200.0, -2450.0));
+        // -O0 only counts the complete object c
+        assert_eq!(impact("-O0"), (1, -100.0, -1900.0));
+        assert_eq!(report.impacts.len(), 3);
+
+        // b, the slowest object, is the only one built without -g
+        let spreads: Vec<(&str, f64)> = report
+            .groups
+            .iter()
+            .map(|group| (group.group, group.spread))
+            .collect();
+        assert_eq!(spreads, vec![("debug", 2650.0), ("optimization", 2000.0)]);
+        let debug: Vec<&str> = report.groups[0]
+            .settings
+            .iter()
+            .map(|setting| setting.flags.as_str())
+            .collect();
+        assert_eq!(debug, vec!["", "-g"]);
+    }
+
+    #[test]
+    fn test_timeline_report() {
+        // Two objects overlap from 5 to 10, then nothing runs from 20 to 30
+        let report = TimelineReport::new(&[(0, 10), (5, 20), (30, 40)], 0);
+        assert_eq!((report.start, report.end, report.busy), (0, 40, 35));
+        assert_eq!(report.peak, 2);
+        assert_eq!(report.efficiency(), 35.0 / 40.0);
+
+        assert_eq!(
+            report.concurrency.into_iter().collect::<Vec<_>>(),
+            vec![(0, 10), (1, 25), (2, 5)]
+        );
+        assert_eq!(report.gaps, vec![(20, 10)]);
+        assert_eq!(report.slices[0], 1.0);
+        assert_eq!(report.slices[12], 0.0);
+
+        // The time of a link is not compile time
+        let mut conn = get_connection(":memory:").unwrap();
+        for (path, start, end) in [("/build/a", 0, 10), ("/build/b", 5, 20)] {
+            NewObject::new(path, 0, 0, 0, false, Some(start), Some(end))
+                .insert(&mut conn)
+                .unwrap();
+        }
+        diesel::insert_into(links::table)
+            .values(&NewLink {
+                path: "/build/app",
+                total_time: 20,
+                partial: false,
+                start_time: Some(20),
+                end_time: Some(40),
+            })
+            .execute(&mut conn)
+            .unwrap();
+        let report = TimelineReport::load(&mut conn).unwrap();
+        assert_eq!((report.links, report.busy, report.link_time), (1, 45, 20));
+        assert_eq!(report.efficiency(), 25.0 / 40.0);
+    }
+
+    #[test]
+    fn test_ninja_report() {
+        let mut conn = get_connection(":memory:").unwrap();
+        for (object, total_time, start_time) in [
+            ("/build/a", 800_000, 1_000_050_000),
+            ("/build/b", 250_000, 1_000_400_000),
+            ("/build/c", 280_000, 1_000_020_000),
+        ] {
+            NewObject::new(object, total_time, 0, 0, false, Some(start_time), None)
+                .insert(&mut conn)
+                .unwrap();
+        }
+        // c.o was last built by an earlier build, whose times also start at 0
+        let log = "# ninja log v5\n\
+            1000\t1300\t0\tc.o\t4\n\
+            100\t400\t0\tb.o\t2\n\
+            0\t900\t0\ta.o\t1\n\
+            900\t1400\t0\tapp\t3\n";
+        import_ninja_log(
+            &parse(log.as_bytes()).unwrap(),
+            Path::new("/build"),
+            &mut conn,
+        )
+        .unwrap();
+
+        let report = NinjaReport::load(&mut conn).unwrap();
+        assert_eq!(report.untraced, vec![("app".to_string(), 500_000)]);
+        let steps: Vec<(&str, i64, Option<i64>)> = report
+            .traced
+            .iter()
+            .map(|step| (step.object.as_str(), step.overhead(), step.launch_delay))
+            .collect();
+        // b started tracing 300 ms after its step, a 50 ms after its own, c the quickest of
+        // its build
+        assert_eq!(
+            steps,
+            vec![
+                ("/build/a", 100_000, Some(0)),
+                ("/build/b", 50_000, Some(250_000)),
+                ("/build/c", 20_000, Some(0))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_critical_path_report() {
+        let scratch = TestDir::new("critical-path");
+        let directory = scratch.path();
+        let build_graph = scratch.write(
+            "build.ninja",
+            "rule cc\n  command = cc\nrule link\n  command = ld\n\
+            build a.o: cc a.c\nbuild b.o: cc b.c\nbuild app: link a.o b.o\n",
+        );
+
+        let mut conn = get_connection(":memory:").unwrap();
+        // b.o is not in the log, its compile time is used instead
+        NewObject::new(
+            directory.join("b").to_str().unwrap(),
+            2_000_000,
+            0,
+            0,
+            false,
+            None,
+            None,
+        )
+        .insert(&mut conn)
+        .unwrap();
+        let log = "# ninja log v5\n0\t3000\t0\ta.o\t1\n3000\t4000\t0\tapp\t2\n";
+        import_ninja_log(&parse(log.as_bytes()).unwrap(), directory, &mut conn).unwrap();
+
+        // A scenario needs a percentage, and the build graph has to exist
+        for (graph, scenario) in [
+            (build_graph.clone(), "a.o"),
+            (build_graph.clone(), "a.o:fast"),
+            (directory.join("missing.ninja"), "a.o:50"),
+        ] {
+            assert!(
+                CriticalPathReport::load(&graph, &[scenario.to_string()], 2, &mut conn).is_err()
+            );
+        }
+
+        let report =
+            CriticalPathReport::load(&build_graph, &["a.o:50".to_string()], 2, &mut conn).unwrap();
+
+        assert_eq!(report.total, 6_000_000);
+        assert_eq!(
+            report.path,
+            vec![
+                ("a.o".to_string(), 3_000_000),
+                ("app".to_string(), 1_000_000)
+            ]
+        );
+        assert_eq!(report.simulations[&1], 6_000_000);
+        assert_eq!(report.simulations[&2], 4_000_000);
+        assert_eq!(report.what_ifs[0].steps, 1);
+        assert_eq!(report.what_ifs[0].critical_path, 3_000_000);
+    }
+
+    #[test]
+    fn test_build_report() {
+        let mut conn = get_connection(":memory:").unwrap();
+        NewObject::new(
+            "/build/a", 3_000_000, 2_000_000, 1_000_000, false, None, None,
+        )
+        .insert(&mut conn)
+        .unwrap();
+        diesel::insert_into(links::table)
+            .values(&NewLink {
+                path: "/build/app",
+                total_time: 1_000_000,
+                partial: false,
+                start_time: None,
+                end_time: None,
+            })
+            .execute(&mut conn)
+            .unwrap();
+        let units = cargo_timings::parse(
+            r#"const UNIT_DATA = [
+                {"name": "serde", "version": "1.0.190", "mode": "build", "target": "", "start": 0.0,
+                 "duration": 3.0, "rmeta_time": 1.0},
+                {"name": "app", "version": "0.1.0", "mode": "run-custom-build",
+                 "target": " build script", "start": 0.0, "duration": 2.0, "rmeta_time": null}
+            ];"#,
+        )
+        .unwrap();
+        import_cargo_timings(&units, &mut conn).unwrap();
+
+        let report = BuildReport::load(&mut conn).unwrap();
+        assert_eq!(report.cpp_time(), 4_000_000);
+        assert_eq!(
+            (report.rust_time, report.rust_frontend, report.rust_codegen),
+            (5_000_000, 1_000_000, 2_000_000)
+        );
+        assert_eq!(
+            report.top_units[1],
+            BuildUnit {
+                unit: "app v0.1.0 build script (run-custom-build)".to_string(),
+                duration: 2_000_000,
+                rmeta_time: None,
+            }
+        );
+        assert!(report
+            .to_string()
+            .starts_with("C++: 1 objects, 3.00 s compiling"));
+    }
+}
@@ -1,10 +1,85 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    aggregates (aggregate, name) {
+        aggregate -> Text,
+        name -> Text,
+        duration -> Integer,
+        count -> Integer,
+        exclusive_duration -> Integer,
+    }
+}
+
+diesel::table! {
+    backend_phase (name) {
+        name -> Text,
+        duration -> Integer,
+        count -> Integer,
+        exclusive_duration -> Integer,
+    }
+}
+
+diesel::table! {
+    cargo_units (name, version, target, mode) {
+        name -> Text,
+        version -> Text,
+        target -> Text,
+        mode -> Text,
+        start_time -> Nullable<BigInt>,
+        duration -> BigInt,
+        rmeta_time -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    codegen_function (name) {
+        name -> Text,
+        duration -> Integer,
+        count -> Integer,
+        exclusive_duration -> Integer,
+    }
+}
+
+diesel::table! {
+    compile_commands (object) {
+        object -> Text,
+        source -> Text,
+        output -> Text,
+        directory -> Text,
+        command -> Text,
+    }
+}
+
+diesel::table! {
+    gcc_passes (object, name) {
+        object -> Text,
+        name -> Text,
+        usr -> Integer,
+        sys -> Integer,
+        wall -> Integer,
+        ggc -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    include_edges (id) {
+        id -> Integer,
+        object -> Text,
+        includer -> Nullable<Text>,
+        included -> Text,
+        depth -> Integer,
+        duration -> Integer,
+        exclusive_duration -> Integer,
+        count -> Integer,
+    }
+}
+
 diesel::table! {
     instantiate_class (name) {
         name -> Text,
         duration -> Integer,
         count -> Integer,
+        exclusive_duration -> Integer,
     }
 }
 
@@ -13,6 +88,60 @@ diesel::table! {
         name -> Text,
         duration -> Integer,
         count -> Integer,
+        exclusive_duration -> Integer,
+    }
+}
+
+diesel::table! {
+    link_phases (link, name) {
+        link -> Text,
+        name -> Text,
+        duration -> Integer,
+        count -> Integer,
+    }
+}
+
+diesel::table! {
+    links (path) {
+        path -> Text,
+        total_time -> Integer,
+        partial -> Bool,
+        start_time -> Nullable<BigInt>,
+        end_time -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    ninja_steps (output) {
+        output -> Text,
+        start_ms -> BigInt,
+        end_ms -> BigInt,
+        mtime -> BigInt,
+        command_hash -> Text,
+        object -> Nullable<Text>,
+        build -> BigInt,
+    }
+}
+
+diesel::table! {
+    object_aggregates (object, aggregate, name) {
+        object -> Text,
+        aggregate -> Text,
+        name -> Text,
+        duration -> Integer,
+        exclusive_duration -> Integer,
+        count -> Integer,
+    }
+}
+
+diesel::table! {
+    object_facts (object, kind, name) {
+        object -> Text,
+        kind -> Text,
+        name -> Text,
+        duration -> Integer,
+        exclusive_duration -> Integer,
+        count -> Integer,
     }
 }
 
@@ -22,6 +151,18 @@ diesel::table! {
         total_time -> Integer,
         frontend -> Integer,
         backend -> Integer,
+        partial -> Bool,
+        start_time -> Nullable<BigInt>,
+        end_time -> Nullable<BigInt>,
+    }
+}
+
+diesel::table! {
+    opt_function (name) {
+        name -> Text,
+        duration -> Integer,
+        count -> Integer,
+        exclusive_duration -> Integer,
     }
 }
 
@@ -30,6 +171,7 @@ diesel::table! {
         name -> Text,
         duration -> Integer,
         count -> Integer,
+        exclusive_duration -> Integer,
     }
 }
 
@@ -38,6 +180,30 @@ diesel::table! {
         name -> Text,
         duration -> Integer,
         count -> Integer,
+        exclusive_duration -> Integer,
+    }
+}
+
+diesel::table! {
+    raw_event_objects (id) {
+        id -> Integer,
+        object -> Text,
+    }
+}
+
+diesel::table! {
+    raw_events (id) {
+        id -> Integer,
+        object_id -> Integer,
+        name -> Text,
+        category -> Nullable<Text>,
+        phase -> Text,
+        ts -> BigInt,
+        dur -> Nullable<BigInt>,
+        pid -> BigInt,
+        tid -> BigInt,
+        detail -> Nullable<Text>,
+        args -> Nullable<Text>,
     }
 }
 
@@ -46,14 +212,76 @@ diesel::table! {
         path -> Text,
         duration -> Integer,
         count -> Integer,
+        exclusive_duration -> Integer,
+    }
+}
+
+diesel::table! {
+    thinlto_modules (link, module) {
+        link -> Text,
+        module -> Text,
+        duration -> Integer,
+        count -> Integer,
+    }
+}
+
+diesel::table! {
+    trace_files (path) {
+        path -> Text,
+        object -> Text,
+        size -> BigInt,
+        mtime -> BigInt,
+        hash -> Text,
+        status -> Text,
+        raw_events -> Bool,
+        aggregates -> Text,
+    }
+}
+
+diesel::table! {
+    trace_metadata (object) {
+        object -> Text,
+        compiler -> Nullable<Text>,
+        process_name -> Nullable<Text>,
+        beginning_of_time -> Nullable<BigInt>,
+        display_time_unit -> Nullable<Text>,
+        compiler_version -> Nullable<Text>,
+    }
+}
+
+diesel::table! {
+    trace_threads (object, pid, tid) {
+        object -> Text,
+        pid -> BigInt,
+        tid -> BigInt,
+        name -> Text,
     }
 }
 
 diesel::allow_tables_to_appear_in_same_query!(
+    aggregates,
+    backend_phase,
+    cargo_units,
+    codegen_function,
+    compile_commands,
+    gcc_passes,
+    include_edges,
     instantiate_class,
     instantiate_function,
+    link_phases,
+    links,
+    ninja_steps,
+    object_aggregates,
+    object_facts,
     objects,
+    opt_function,
     parse_class,
     parse_template,
+    raw_event_objects,
+    raw_events,
     source,
+    thinlto_modules,
+    trace_files,
+    trace_metadata,
+    trace_threads,
 );
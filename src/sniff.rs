@@ -0,0 +1,125 @@
+/// Tell time traces apart from the other JSON files of a build tree by their beginning
+use std::{
+    fmt,
+    io::{self, Read},
+    path::Path,
+};
+
+use crate::file_parser::{is_time_report, open_trace};
+
+/// Number of bytes read, after decompression, to decide whether a file is a trace
+const SNIFF_SIZE: u64 = 64 * 1024;
+
+/// Why a file was not considered a trace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SkipReason {
+    /// The file has no content
+    Empty,
+    /// The file does not start as a JSON object or array
+    NotJson,
+    /// The file is JSON, but has neither `traceEvents` nor events with a phase and a timestamp
+    NoTraceEvents,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SkipReason::Empty => "empty",
+            SkipReason::NotJson => "not JSON",
+            SkipReason::NoTraceEvents => "no trace events",
+        })
+    }
+}
+
+/// Look at the beginning of a file, `None` if it looks like a trace
+pub fn sniff(prefix: &[u8]) -> Option<SkipReason> {
+    let prefix = prefix.strip_prefix(b"\xef\xbb\xbf").unwrap_or(prefix);
+    let start = match prefix.iter().position(|byte| !byte.is_ascii_whitespace()) {
+        Some(start) => start,
+        None => return Some(SkipReason::Empty),
+    };
+    if !matches!(prefix[start], b'{' | b'[') {
+        return Some(SkipReason::NotJson);
+    }
+
+    let contains = |needle: &[u8]| prefix.windows(needle.len()).any(|window| window == needle);
+    if contains(b"\"traceEvents\"") || (contains(b"\"ph\"") && contains(b"\"ts\"")) {
+        None
+    } else {
+        Some(SkipReason::NoTraceEvents)
+    }
+}
+
+/// Read the beginning of the file, decompressed, and [`sniff`] it; time reports are not sniffed
+pub fn sniff_trace(path: &Path) -> io::Result<Option<SkipReason>> {
+    if is_time_report(path) {
+        return Ok(None);
+    }
+    let mut prefix = Vec::new();
+    open_trace(path)?
+        .take(SNIFF_SIZE)
+        .read_to_end(&mut prefix)?;
+    Ok(sniff(&prefix))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use super::{sniff, sniff_trace, SkipReason, SNIFF_SIZE};
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn test_sniff() {
+        assert_eq!(
+            sniff(br#"{"traceEvents": [{"pid": 1, "ph": "X", "ts": 0}]}"#),
+            None
+        );
+        assert_eq!(
+            sniff(br#"[{"pid": 1, "ph": "B", "ts": 0, "name": "Source"}"#),
+            None
+        );
+        assert_eq!(
+            sniff(br#"{"name": "Frontend", "ph": "X", "ts": 0, "dur": 1}"#),
+            None
+        );
+        assert_eq!(sniff(b" \n"), Some(SkipReason::Empty));
+        assert_eq!(sniff(b"ninja log"), Some(SkipReason::NotJson));
+        assert_eq!(
+            sniff(b"\nTime variable                 usr           sys          wall\n"),
+            Some(SkipReason::NotJson)
+        );
+        assert_eq!(
+            sniff(br#"[{"directory": "/build", "command": "clang++ -c a.cpp", "file": "a.cpp"}]"#),
+            Some(SkipReason::NoTraceEvents)
+        );
+    }
+
+    #[test]
+    fn test_sniff_trace() {
+        let directory = TestDir::new("sniff");
+
+        // The table of a time report comes after the diagnostics, however long they are
+        let warning = "a.cpp:3:7: warning: unused variable 'x' [-Wunused-variable]\n";
+        let diagnostics = warning.repeat(SNIFF_SIZE as usize / warning.len() + 1);
+        let report = directory.write(
+            "a.cpp.time-report",
+            format!("{}Time variable    usr    sys    wall\n", diagnostics),
+        );
+        assert_eq!(sniff_trace(&report).unwrap(), None);
+
+        // Compressed files are sniffed once decompressed
+        let mut gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        gz.write_all(br#"{"version": 1, "name": "app"}"#).unwrap();
+        let manifest = directory.write("vcpkg.json.gz", gz.finish().unwrap());
+        assert_eq!(
+            sniff_trace(&manifest).unwrap(),
+            Some(SkipReason::NoTraceEvents)
+        );
+
+        // A missing file, or one not in the compression it is named after, is an error
+        assert!(sniff_trace(&directory.path().join("missing.json")).is_err());
+        let plain = directory.write("b.cpp.json.gz", r#"{"traceEvents": []}"#);
+        assert!(sniff_trace(&plain).is_err());
+    }
+}
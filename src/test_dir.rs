@@ -0,0 +1,35 @@
+/// Scratch directories of the tests
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// A directory under the temporary directory, removed when dropped
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    /// `name` tells apart the directories of the tests running at the same time
+    pub fn new(name: &str) -> TestDir {
+        let path = std::env::temp_dir().join(format!("timetracer-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TestDir(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+
+    /// Write a file of the directory and return its path
+    pub fn write(&self, name: &str, content: impl AsRef<[u8]>) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
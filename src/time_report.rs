@@ -0,0 +1,202 @@
+/// Read the output of GCC `-ftime-report`, captured to a `.time-report` file
+use std::{collections::BTreeMap, error::Error, io::BufRead};
+
+/// Phases of the frontend, mapped onto `objects.frontend`
+const FRONTEND_PHASES: [&str; 4] = [
+    "phase setup",
+    "phase parsing",
+    "phase lang. deferred",
+    "phase late parsing cleanups",
+];
+
+/// Phases of the backend, mapped onto `objects.backend`
+const BACKEND_PHASES: [&str; 5] = [
+    "phase opt and generate",
+    "phase last asm",
+    "phase stream in",
+    "phase stream out",
+    "phase finalize",
+];
+
+/// A row of the table, times in microseconds
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Pass {
+    pub usr: i32,
+    pub sys: i32,
+    pub wall: i32,
+    /// Memory collected, in kB
+    pub ggc: Option<i64>,
+}
+
+impl Pass {
+    fn add(&mut self, other: &Pass) {
+        self.usr = self.usr.saturating_add(other.usr);
+        self.sys = self.sys.saturating_add(other.sys);
+        self.wall = self.wall.saturating_add(other.wall);
+        self.ggc = match (self.ggc, other.ggc) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or(0) + b.unwrap_or(0)),
+        };
+    }
+}
+
+/// The table of one compilation
+#[derive(Debug, Default)]
+pub struct TimeReport {
+    /// Per phase or pass, `|` of the nested passes left out; rows of the same name are summed
+    pub passes: BTreeMap<String, Pass>,
+    /// The `TOTAL` row, missing when the output was cut short
+    pub total: Option<Pass>,
+}
+
+impl TimeReport {
+    fn wall_of(&self, phases: &[&str]) -> i32 {
+        phases
+            .iter()
+            .filter_map(|phase| self.passes.get(*phase))
+            .fold(0, |wall, pass| wall.saturating_add(pass.wall))
+    }
+
+    /// Wall time of the frontend phases
+    pub fn frontend(&self) -> i32 {
+        self.wall_of(&FRONTEND_PHASES)
+    }
+
+    /// Wall time of the backend phases
+    pub fn backend(&self) -> i32 {
+        self.wall_of(&BACKEND_PHASES)
+    }
+}
+
+fn seconds(value: f64) -> i32 {
+    (value * 1_000_000.0).round() as i32
+}
+
+/// Memory with its unit, as the newer versions write it, in kB
+fn memory(token: &str) -> Option<i64> {
+    let (number, scale) = match token.char_indices().last()? {
+        (index, 'k') => (&token[..index], 1.0),
+        (index, 'M') => (&token[..index], 1024.0),
+        (index, 'G') => (&token[..index], 1024.0 * 1024.0),
+        _ => return None,
+    };
+    number
+        .parse::<f64>()
+        .ok()
+        .map(|number| (number * scale).round() as i64)
+}
+
+/// The times of a row, the part after the colon; `None` if it has no user, system and wall time
+fn parse_row(values: &str) -> Option<Pass> {
+    let mut outside = String::new();
+    let mut depth = 0;
+    for c in values.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if depth == 0 => outside.push(c),
+            _ => {}
+        }
+    }
+    let tokens: Vec<&str> = outside.split_whitespace().collect();
+    let numbers: Vec<f64> = tokens
+        .iter()
+        .filter_map(|token| token.parse().ok())
+        .collect();
+    if numbers.len() < 3 {
+        return None;
+    }
+    let ggc = match numbers.get(3) {
+        Some(kb) => Some(kb.round() as i64),
+        None => tokens.iter().find_map(|token| memory(token)),
+    };
+    Some(Pass {
+        usr: seconds(numbers[0]),
+        sys: seconds(numbers[1]),
+        wall: seconds(numbers[2]),
+        ggc,
+    })
+}
+
+/// Read the table, the lines before its header, e.g. diagnostics, are ignored
+pub fn parse<R: BufRead>(reader: R) -> Result<TimeReport, Box<dyn Error + Send + Sync + 'static>> {
+    let mut report = TimeReport::default();
+    let mut in_table = false;
+    for line in reader.lines() {
+        let line = line?;
+        let header = line.trim_start();
+        if header.starts_with("Time variable") || header.starts_with("Execution times") {
+            in_table = true;
+            continue;
+        }
+        if !in_table {
+            continue;
+        }
+        let Some((name, values)) = line.rsplit_once(':') else {
+            continue;
+        };
+        let Some(pass) = parse_row(values) else {
+            continue;
+        };
+        let name = name.trim().trim_start_matches('|').trim();
+        if name == "TOTAL" {
+            report.total = Some(pass);
+            break;
+        }
+        report
+            .passes
+            .entry(name.to_string())
+            .or_default()
+            .add(&pass);
+    }
+    if !in_table {
+        return Err("No -ftime-report table".into());
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Pass};
+
+    #[test]
+    fn test_time_report() {
+        let output = "a.cpp: In function 'int main()':
+a.cpp:3:7: warning: unused variable 'x' [-Wunused-variable]
+
+Time variable                                   usr           sys          wall           GGC
+ phase setup                        :   0.00 (  0%)   0.00 (  0%)   0.01 (  1%)  1564k (  2%)
+ phase parsing                      :   0.30 ( 38%)   0.10 ( 56%)   0.41 ( 41%)    48M ( 60%)
+ phase lang. deferred               :   0.04 (  5%)   0.00 (  0%)   0.05 (  5%)  4000k (  5%)
+ phase opt and generate             :   0.45 ( 57%)   0.08 ( 44%)   0.52 ( 53%) 27000k ( 33%)
+ |name lookup                       :   0.05 (  6%)   0.01 (  6%)   0.07 (  7%)  2412k (  3%)
+ callgraph construction             :   0.02 (  3%)   0.00 (  0%)   0.02 (  2%)  1000k (  1%)
+ TOTAL                              :   0.79          0.18          0.99         83000k
+";
+        let report = parse(output.as_bytes()).unwrap();
+        assert_eq!(report.passes.len(), 6);
+        assert_eq!(
+            report.passes["name lookup"],
+            Pass {
+                usr: 50000,
+                sys: 10000,
+                wall: 70000,
+                ggc: Some(2412),
+            }
+        );
+        assert_eq!(report.passes["phase parsing"].ggc, Some(49152));
+        assert_eq!(report.frontend(), 470000);
+        assert_eq!(report.backend(), 520000);
+        assert_eq!(report.total.map(|total| total.wall), Some(990000));
+
+        // Older versions label every column
+        let old = "Execution times (seconds)
+ phase parsing           :   0.30 (38%) usr   0.10 (56%) sys   0.41 (41%) wall   50000 kB (60%) ggc
+";
+        let report = parse(old.as_bytes()).unwrap();
+        assert_eq!(report.frontend(), 410000);
+        assert_eq!(report.passes["phase parsing"].ggc, Some(50000));
+        assert!(report.total.is_none());
+        assert!(parse("no table\n".as_bytes()).is_err());
+    }
+}
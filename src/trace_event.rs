@@ -2,7 +2,12 @@
 extern crate serde;
 extern crate serde_json;
 
-use serde::{Deserialize, Serialize};
+use std::{fmt, io::Read};
+
+use serde::{
+    de::{DeserializeSeed, Error as _, IgnoredAny, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub enum EventType {
@@ -107,8 +112,46 @@ pub enum EventType {
     RPparenthesis,
 }
 
+impl EventType {
+    /// The `ph` value of the event type
+    #[allow(deprecated)]
+    pub fn code(&self) -> &'static str {
+        match self {
+            EventType::B => "B",
+            EventType::E => "E",
+            EventType::X => "X",
+            EventType::i => "i",
+            EventType::I => "I",
+            EventType::C => "C",
+            EventType::b => "b",
+            EventType::n => "n",
+            EventType::e => "e",
+            EventType::S => "S",
+            EventType::T => "T",
+            EventType::p => "p",
+            EventType::F => "F",
+            EventType::s => "s",
+            EventType::t => "t",
+            EventType::f => "f",
+            EventType::P => "P",
+            EventType::N => "N",
+            EventType::O => "O",
+            EventType::D => "D",
+            EventType::M => "M",
+            EventType::V => "V",
+            EventType::v => "v",
+            EventType::R => "R",
+            EventType::c => "c",
+            EventType::LParenthesis => "(",
+            EventType::RPparenthesis => ")",
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TraceEvent {
+    /// End events may omit the name
+    #[serde(default)]
     pub name: String,
     #[serde(alias = "cat")]
     pub category: Option<String>,
@@ -131,13 +174,184 @@ pub struct TraceEvents {
     pub display_time_unit: Option<String>,
     #[serde(alias = "systemTraceEvents")]
     pub system_trace_events: Option<String>,
+    /// Wall clock time, in microseconds since the UNIX epoch, the timestamps are relative to
+    #[serde(alias = "beginningOfTime")]
+    pub beginning_of_time: Option<i64>,
+}
+
+/// The variants of the Trace Event Format
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    /// `{"traceEvents": [...], ...}`, as written by clang
+    #[default]
+    Object,
+    /// A bare array of events
+    Array,
+    /// One event per line
+    JsonLines,
+}
+
+/// Top level fields of a trace, everything except the events themselves
+#[derive(Debug, Default)]
+pub struct TraceHeader {
+    pub format: TraceFormat,
+    pub display_time_unit: Option<String>,
+    pub system_trace_events: Option<String>,
+    pub beginning_of_time: Option<i64>,
+}
+
+/// Fields of [`TraceEvent`], under their serialized names and aliases
+const EVENT_FIELDS: [&str; 12] = [
+    "name",
+    "cat",
+    "category",
+    "ph",
+    "phase",
+    "ts",
+    "timestamp",
+    "pid",
+    "tid",
+    "dur",
+    "duration",
+    "args",
+];
+
+/// Feeds each element of an array of events to the callback as it is decoded
+struct TraceEventsSeed<'f, F> {
+    callback: &'f mut F,
+}
+
+impl<'de, 'f, F: FnMut(TraceEvent)> DeserializeSeed<'de> for TraceEventsSeed<'f, F> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'f, F: FnMut(TraceEvent)> Visitor<'de> for TraceEventsSeed<'f, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of trace events")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(trace_event) = seq.next_element::<TraceEvent>()? {
+            (self.callback)(trace_event);
+        }
+        Ok(())
+    }
+}
+
+/// Walks one top level value: an array, an object with `traceEvents` or a single event
+struct TraceVisitor<'f, 'h, F> {
+    callback: &'f mut F,
+    header: &'h mut TraceHeader,
+}
+
+impl<'de, 'f, 'h, F: FnMut(TraceEvent)> Visitor<'de> for TraceVisitor<'f, 'h, F> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a trace object with traceEvents, an array of events or an event")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<(), A::Error> {
+        self.header.format = TraceFormat::Array;
+        TraceEventsSeed {
+            callback: self.callback,
+        }
+        .visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let mut has_trace_events = false;
+        let mut event_fields = serde_json::Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            match key.as_str() {
+                "traceEvents" | "trace_events" => {
+                    map.next_value_seed(TraceEventsSeed {
+                        callback: &mut *self.callback,
+                    })?;
+                    has_trace_events = true;
+                }
+                "displayTimeUnit" | "display_time_unit" => {
+                    self.header.display_time_unit = map.next_value()?
+                }
+                "systemTraceEvents" | "system_trace_events" => {
+                    self.header.system_trace_events = map.next_value()?
+                }
+                "beginningOfTime" | "beginning_of_time" => {
+                    self.header.beginning_of_time = map.next_value()?
+                }
+                field if EVENT_FIELDS.contains(&field) => {
+                    event_fields.insert(key, map.next_value()?);
+                }
+                _ => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        if has_trace_events {
+            self.header.format = TraceFormat::Object;
+        } else if !event_fields.is_empty() {
+            self.header.format = TraceFormat::JsonLines;
+            let trace_event = serde_json::from_value(serde_json::Value::Object(event_fields))
+                .map_err(A::Error::custom)?;
+            (self.callback)(trace_event);
+        } else {
+            return Err(A::Error::missing_field("traceEvents"));
+        }
+        Ok(())
+    }
+}
+
+/// Deserialize a trace, handing every event to `callback` instead of collecting them
+pub fn stream_trace_events<R: Read, F: FnMut(TraceEvent)>(
+    reader: R,
+    mut callback: F,
+) -> Result<TraceHeader, serde_json::Error> {
+    let mut header = TraceHeader::default();
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    loop {
+        deserializer.deserialize_any(TraceVisitor {
+            callback: &mut callback,
+            header: &mut header,
+        })?;
+        // Only JSON lines have several top level values; `end` leaves the next one unread
+        if deserializer.end().is_ok() {
+            break;
+        }
+    }
+    Ok(header)
+}
+
+/// Whether the error of [`stream_trace_events`] comes from the trace ending too early
+pub fn is_truncated(error: &serde_json::Error) -> bool {
+    error.is_eof() || error.io_error_kind() == Some(std::io::ErrorKind::UnexpectedEof)
+}
+
+impl TraceEvents {
+    /// Read a whole trace in any of the formats [`stream_trace_events`] accepts
+    pub fn from_reader<R: Read>(reader: R) -> Result<TraceEvents, serde_json::Error> {
+        let mut trace_events = Vec::new();
+        let header = stream_trace_events(reader, |trace_event| trace_events.push(trace_event))?;
+        Ok(TraceEvents {
+            trace_events,
+            display_time_unit: header.display_time_unit,
+            system_trace_events: header.system_trace_events,
+            beginning_of_time: header.beginning_of_time,
+        })
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::trace_event::EventType;
 
-    use super::TraceEvents;
+    use super::{stream_trace_events, TraceEvents, TraceFormat};
 
     #[test]
     fn test_deserialize_trace_event() {
@@ -177,4 +391,54 @@ mod test {
             "/usr/include/features.h"
         );
     }
+
+    #[test]
+    fn test_stream_trace_events() {
+        let trace = r#"
+{
+    "beginningOfTime": 1671900000000000,
+    "traceEvents": [
+        {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 5, "name": "Source", "args": {"detail": "a.h"}},
+        {"pid": 1, "tid": 1, "ph": "X", "ts": 20, "dur": 7, "name": "ParseClass", "args": {"detail": "A"}}
+    ],
+    "displayTimeUnit": "ns"
+}"#;
+        let mut names = Vec::new();
+        let header = stream_trace_events(trace.as_bytes(), |event| names.push(event.name)).unwrap();
+
+        assert_eq!(names, vec!["Source", "ParseClass"]);
+        assert_eq!(header.display_time_unit.unwrap(), "ns");
+        assert_eq!(header.beginning_of_time.unwrap(), 1671900000000000);
+    }
+
+    #[test]
+    fn test_array_and_json_lines_formats() {
+        let array = r#"[
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 5, "name": "Source", "args": {"detail": "a.h"}},
+            {"pid": 1, "tid": 1, "ph": "X", "ts": 20, "dur": 7, "name": "ParseClass", "args": {"detail": "A"}}
+        ]"#;
+        let json_lines = r#"{"pid": 1, "tid": 1, "ph": "X", "ts": 10, "dur": 5, "name": "Source", "args": {"detail": "a.h"}}
+{"pid": 1, "tid": 1, "ph": "X", "ts": 20, "dur": 7, "name": "ParseClass", "args": {"detail": "A"}}
+"#;
+        for (trace, format) in [
+            (array, TraceFormat::Array),
+            (json_lines, TraceFormat::JsonLines),
+        ] {
+            let mut names = Vec::new();
+            let header =
+                stream_trace_events(trace.as_bytes(), |event| names.push(event.name)).unwrap();
+            assert_eq!(header.format, format);
+            assert_eq!(names, vec!["Source", "ParseClass"]);
+        }
+
+        assert_eq!(
+            TraceEvents::from_reader(array.as_bytes())
+                .unwrap()
+                .trace_events
+                .len(),
+            2
+        );
+        assert!(stream_trace_events(r#"{"version": 1}"#.as_bytes(), |_| {}).is_err());
+        assert!(stream_trace_events("".as_bytes(), |_| {}).is_err());
+    }
 }
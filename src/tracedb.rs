@@ -9,7 +9,11 @@ use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
 use log::debug;
 
 use crate::schema::{
-    instantiate_class, instantiate_function, objects, parse_class, parse_template, source,
+    aggregates, backend_phase, cargo_units, codegen_function, compile_commands, gcc_passes,
+    include_edges, instantiate_class, instantiate_function, link_phases, links, ninja_steps,
+    object_aggregates, object_facts, objects, opt_function, parse_class, parse_template,
+    raw_event_objects, raw_events, source, thinlto_modules, trace_files, trace_metadata,
+    trace_threads,
 };
 
 pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
@@ -30,17 +34,39 @@ pub fn get_connection(
     let mut connection = SqliteConnection::establish(target)?;
     run_migrations(&mut connection)?;
 
-    // At this stage, we delete all tables
-    diesel::delete(objects::table).execute(&mut connection)?;
-    diesel::delete(source::table).execute(&mut connection)?;
-    diesel::delete(parse_class::table).execute(&mut connection)?;
-    diesel::delete(parse_template::table).execute(&mut connection)?;
-    diesel::delete(instantiate_class::table).execute(&mut connection)?;
-    diesel::delete(instantiate_function::table).execute(&mut connection)?;
-
     Ok(connection)
 }
 
+/// Delete everything ingested from traces, the imported build logs are kept
+pub fn clear_tables(
+    connection: &mut SqliteConnection,
+) -> Result<(), Box<dyn Error + Send + Sync + 'static>> {
+    diesel::delete(objects::table).execute(connection)?;
+    diesel::delete(links::table).execute(connection)?;
+    diesel::delete(link_phases::table).execute(connection)?;
+    diesel::delete(thinlto_modules::table).execute(connection)?;
+    diesel::delete(source::table).execute(connection)?;
+    diesel::delete(parse_class::table).execute(connection)?;
+    diesel::delete(parse_template::table).execute(connection)?;
+    diesel::delete(instantiate_class::table).execute(connection)?;
+    diesel::delete(instantiate_function::table).execute(connection)?;
+    diesel::delete(codegen_function::table).execute(connection)?;
+    diesel::delete(opt_function::table).execute(connection)?;
+    diesel::delete(backend_phase::table).execute(connection)?;
+    diesel::delete(include_edges::table).execute(connection)?;
+    diesel::delete(gcc_passes::table).execute(connection)?;
+    diesel::delete(object_facts::table).execute(connection)?;
+    diesel::delete(aggregates::table).execute(connection)?;
+    diesel::delete(object_aggregates::table).execute(connection)?;
+    diesel::delete(trace_metadata::table).execute(connection)?;
+    diesel::delete(trace_threads::table).execute(connection)?;
+    diesel::delete(raw_events::table).execute(connection)?;
+    diesel::delete(raw_event_objects::table).execute(connection)?;
+    diesel::delete(trace_files::table).execute(connection)?;
+
+    Ok(())
+}
+
 #[derive(Queryable)]
 #[diesel(table_name = objects)]
 pub struct Object {
@@ -48,6 +74,11 @@ pub struct Object {
     pub total_time: i32,
     pub frontend: i32,
     pub backend: i32,
+    /// The trace was truncated, only the events before the truncation were stored
+    pub partial: bool,
+    /// When the compilation started and ended, in microseconds since the UNIX epoch
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
 }
 
 #[derive(Insertable)]
@@ -57,15 +88,29 @@ pub struct NewObject<'a> {
     pub total_time: i32,
     pub frontend: i32,
     pub backend: i32,
+    pub partial: bool,
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
 }
 
 impl<'a> NewObject<'a> {
-    pub fn new(path: &'a str, total_time: i32, frontend: i32, backend: i32) -> Self {
+    pub fn new(
+        path: &'a str,
+        total_time: i32,
+        frontend: i32,
+        backend: i32,
+        partial: bool,
+        start_time: Option<i64>,
+        end_time: Option<i64>,
+    ) -> Self {
         NewObject {
             path,
             total_time,
             frontend,
             backend,
+            partial,
+            start_time,
+            end_time,
         }
     }
 
@@ -88,6 +133,7 @@ pub struct Sources {
     pub path: String,
     pub duration: i32,
     pub count: i32,
+    pub exclusive_duration: i32,
 }
 
 #[derive(Insertable)]
@@ -96,32 +142,7 @@ pub struct NewSource<'a> {
     pub path: &'a str,
     pub duration: i32,
     pub count: i32,
-}
-
-impl<'a> NewSource<'a> {
-    pub fn new(path: &'a str, duration: i32) -> Self {
-        NewSource {
-            path,
-            duration,
-            count: 1,
-        }
-    }
-}
-
-impl<'a> InsertTrait for NewSource<'a> {
-    fn insert(&self, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
-        use source::{count, duration, path};
-        let _ = diesel::insert_into(source::table)
-            .values(self)
-            .on_conflict(path)
-            .do_update()
-            .set((
-                count.eq(count + self.count),
-                duration.eq(duration + self.duration),
-            ))
-            .execute(conn)?;
-        Ok(())
-    }
+    pub exclusive_duration: i32,
 }
 
 #[derive(Queryable)]
@@ -130,6 +151,7 @@ pub struct ParseClass {
     pub name: String,
     pub duration: i32,
     pub count: i32,
+    pub exclusive_duration: i32,
 }
 
 #[derive(Insertable)]
@@ -138,32 +160,7 @@ pub struct NewParseClass<'a> {
     pub name: &'a str,
     pub duration: i32,
     pub count: i32,
-}
-
-impl<'a> NewParseClass<'a> {
-    pub fn new(name: &'a str, duration: i32) -> Self {
-        NewParseClass {
-            name,
-            duration,
-            count: 1,
-        }
-    }
-}
-
-impl<'a> InsertTrait for NewParseClass<'a> {
-    fn insert(&self, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
-        use parse_class::{count, duration, name};
-        let _ = diesel::insert_into(parse_class::table)
-            .values(self)
-            .on_conflict(name)
-            .do_update()
-            .set((
-                count.eq(count + self.count),
-                duration.eq(duration + self.duration),
-            ))
-            .execute(conn)?;
-        Ok(())
-    }
+    pub exclusive_duration: i32,
 }
 
 #[derive(Queryable)]
@@ -172,6 +169,7 @@ pub struct ParseTemplate {
     pub name: String,
     pub duration: i32,
     pub count: i32,
+    pub exclusive_duration: i32,
 }
 
 #[derive(Insertable)]
@@ -180,112 +178,518 @@ pub struct NewParseTemplate<'a> {
     pub name: &'a str,
     pub duration: i32,
     pub count: i32,
+    pub exclusive_duration: i32,
 }
 
-impl<'a> NewParseTemplate<'a> {
-    pub fn new(name: &'a str, duration: i32) -> Self {
-        NewParseTemplate {
-            name,
-            duration,
-            count: 1,
-        }
-    }
+#[derive(Queryable)]
+#[diesel(table_name = instantiate_class)]
+pub struct InstantiateClass {
+    pub name: String,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
 }
 
-impl<'a> InsertTrait for NewParseTemplate<'a> {
-    fn insert(&self, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
-        use parse_template::{count, duration, name};
-        let _ = diesel::insert_into(parse_template::table)
-            .values(self)
-            .on_conflict(name)
-            .do_update()
-            .set((
-                count.eq(count + self.count),
-                duration.eq(duration + self.duration),
-            ))
-            .execute(conn)?;
-        Ok(())
+#[derive(Insertable)]
+#[diesel(table_name = instantiate_class)]
+pub struct NewInstantiateClass<'a> {
+    pub name: &'a str,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+#[derive(Queryable)]
+#[diesel(table_name = instantiate_function)]
+pub struct InstantiateFunction {
+    pub name: String,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = instantiate_function)]
+pub struct NewInstantiateFunction<'a> {
+    pub name: &'a str,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+#[derive(Queryable)]
+#[diesel(table_name = codegen_function)]
+pub struct CodeGenFunction {
+    pub name: String,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = codegen_function)]
+pub struct NewCodeGenFunction<'a> {
+    pub name: &'a str,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+#[derive(Queryable)]
+#[diesel(table_name = opt_function)]
+pub struct OptFunction {
+    pub name: String,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = opt_function)]
+pub struct NewOptFunction<'a> {
+    pub name: &'a str,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+#[derive(Queryable)]
+#[diesel(table_name = backend_phase)]
+pub struct BackendPhase {
+    pub name: String,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = backend_phase)]
+pub struct NewBackendPhase<'a> {
+    pub name: &'a str,
+    pub duration: i32,
+    pub count: i32,
+    pub exclusive_duration: i32,
+}
+
+/// `includer` includes `included` at `depth` in an object, 1 for the main file
+#[derive(Queryable)]
+#[diesel(table_name = include_edges)]
+pub struct IncludeEdge {
+    pub id: i32,
+    pub object: String,
+    pub includer: Option<String>,
+    pub included: String,
+    pub depth: i32,
+    pub duration: i32,
+    pub exclusive_duration: i32,
+    pub count: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = include_edges)]
+pub struct NewIncludeEdge<'a> {
+    pub object: &'a str,
+    pub includer: Option<&'a str>,
+    pub included: &'a str,
+    pub depth: i32,
+    pub duration: i32,
+    pub exclusive_duration: i32,
+    pub count: i32,
+}
+
+/// What one object contributed to a global table, `kind` being the name of that table
+#[derive(Queryable)]
+#[diesel(table_name = object_facts)]
+pub struct ObjectFact {
+    pub object: String,
+    pub kind: String,
+    pub name: String,
+    pub duration: i32,
+    pub exclusive_duration: i32,
+    pub count: i32,
+}
+
+impl ObjectFact {
+    pub fn load_object(
+        object: &str,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<ObjectFact>, Box<dyn Error + 'static>> {
+        Ok(object_facts::table
+            .filter(object_facts::object.eq(object))
+            .load(conn)?)
     }
 }
 
+#[derive(Insertable)]
+#[diesel(table_name = object_facts)]
+pub struct NewObjectFact<'a> {
+    pub object: &'a str,
+    pub kind: &'a str,
+    pub name: &'a str,
+    pub duration: i32,
+    pub exclusive_duration: i32,
+    pub count: i32,
+}
+
+/// Events summed up under an aggregate of the configuration
 #[derive(Queryable)]
-#[diesel(table_name = instantiate_class)]
-pub struct InstantiateClass {
+#[diesel(table_name = aggregates)]
+pub struct Aggregate {
+    pub aggregate: String,
     pub name: String,
     pub duration: i32,
     pub count: i32,
+    pub exclusive_duration: i32,
 }
 
 #[derive(Insertable)]
-#[diesel(table_name = instantiate_class)]
-pub struct NewInstantiateClass<'a> {
+#[diesel(table_name = aggregates)]
+pub struct NewAggregate<'a> {
+    pub aggregate: &'a str,
     pub name: &'a str,
     pub duration: i32,
     pub count: i32,
+    pub exclusive_duration: i32,
 }
 
-impl<'a> NewInstantiateClass<'a> {
-    pub fn new(name: &'a str, duration: i32) -> Self {
-        NewInstantiateClass {
-            name,
-            duration,
-            count: 1,
-        }
+/// What one object contributed to `aggregates`
+#[derive(Queryable)]
+#[diesel(table_name = object_aggregates)]
+pub struct ObjectAggregate {
+    pub object: String,
+    pub aggregate: String,
+    pub name: String,
+    pub duration: i32,
+    pub exclusive_duration: i32,
+    pub count: i32,
+}
+
+impl ObjectAggregate {
+    pub fn load_object(
+        object: &str,
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<ObjectAggregate>, Box<dyn Error + 'static>> {
+        Ok(object_aggregates::table
+            .filter(object_aggregates::object.eq(object))
+            .load(conn)?)
     }
 }
 
-impl<'a> InsertTrait for NewInstantiateClass<'a> {
-    fn insert(&self, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
-        use instantiate_class::{count, duration, name};
-        let _ = diesel::insert_into(instantiate_class::table)
+#[derive(Insertable)]
+#[diesel(table_name = object_aggregates)]
+pub struct NewObjectAggregate<'a> {
+    pub object: &'a str,
+    pub aggregate: &'a str,
+    pub name: &'a str,
+    pub duration: i32,
+    pub exclusive_duration: i32,
+    pub count: i32,
+}
+
+/// The compiler of a trace, and when it started
+#[derive(Queryable)]
+#[diesel(table_name = trace_metadata)]
+pub struct TraceMetadata {
+    pub object: String,
+    pub compiler: Option<String>,
+    pub process_name: Option<String>,
+    pub beginning_of_time: Option<i64>,
+    pub display_time_unit: Option<String>,
+    pub compiler_version: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = trace_metadata)]
+pub struct NewTraceMetadata<'a> {
+    pub object: &'a str,
+    pub compiler: Option<&'a str>,
+    pub process_name: Option<&'a str>,
+    pub beginning_of_time: Option<i64>,
+    pub display_time_unit: Option<&'a str>,
+    pub compiler_version: Option<&'a str>,
+}
+
+impl<'a> NewTraceMetadata<'a> {
+    pub fn insert(&self, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
+        // This should not cause conflict
+        let _ = diesel::insert_into(trace_metadata::table)
             .values(self)
-            .on_conflict(name)
-            .do_update()
-            .set((
-                count.eq(count + self.count),
-                duration.eq(duration + self.duration),
-            ))
             .execute(conn)?;
         Ok(())
     }
 }
 
 #[derive(Queryable)]
-#[diesel(table_name = instantiate_function)]
-pub struct InstantiateFunction {
+#[diesel(table_name = trace_threads)]
+pub struct TraceThread {
+    pub object: String,
+    pub pid: i64,
+    pub tid: i64,
     pub name: String,
-    pub duration: i32,
-    pub count: i32,
 }
 
 #[derive(Insertable)]
-#[diesel(table_name = instantiate_function)]
-pub struct NewInstantiateFunction<'a> {
+#[diesel(table_name = trace_threads)]
+pub struct NewTraceThread<'a> {
+    pub object: &'a str,
+    pub pid: i64,
+    pub tid: i64,
     pub name: &'a str,
-    pub duration: i32,
-    pub count: i32,
 }
 
-impl<'a> NewInstantiateFunction<'a> {
-    pub fn new(name: &'a str, duration: i32) -> Self {
-        NewInstantiateFunction {
-            name,
-            duration,
-            count: 1,
-        }
+/// The id the raw events of an object are stored under, rather than its path on every row
+#[derive(Queryable)]
+#[diesel(table_name = raw_event_objects)]
+pub struct RawEventObject {
+    pub id: i32,
+    pub object: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = raw_event_objects)]
+pub struct NewRawEventObject<'a> {
+    pub object: &'a str,
+}
+
+/// An event stored as found in the trace; `args` is `NULL` when it only held the detail
+#[derive(Queryable)]
+#[diesel(table_name = raw_events)]
+pub struct RawEvent {
+    pub id: i32,
+    pub object_id: i32,
+    pub name: String,
+    pub category: Option<String>,
+    pub phase: String,
+    pub ts: i64,
+    pub dur: Option<i64>,
+    pub pid: i64,
+    pub tid: i64,
+    pub detail: Option<String>,
+    pub args: Option<String>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = raw_events)]
+pub struct NewRawEvent<'a> {
+    pub object_id: i32,
+    pub name: &'a str,
+    pub category: Option<&'a str>,
+    pub phase: &'a str,
+    pub ts: i64,
+    pub dur: Option<i64>,
+    pub pid: i64,
+    pub tid: i64,
+    pub detail: Option<&'a str>,
+    pub args: Option<&'a str>,
+}
+
+#[derive(Queryable)]
+#[diesel(table_name = trace_files)]
+pub struct TraceFile {
+    pub path: String,
+    pub object: String,
+    pub size: i64,
+    pub mtime: i64,
+    pub hash: String,
+    pub status: String,
+    /// Every event of the trace was stored in `raw_events`
+    pub raw_events: bool,
+    /// Fingerprint of the aggregate rules the trace was mapped with
+    pub aggregates: String,
+}
+
+impl TraceFile {
+    pub fn load_all(conn: &mut SqliteConnection) -> Result<Vec<TraceFile>, Box<dyn Error + 'static>> {
+        Ok(trace_files::table.load(conn)?)
     }
+
+    pub fn delete(path: &str, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
+        diesel::delete(trace_files::table.find(path)).execute(conn)?;
+        Ok(())
+    }
+}
+
+#[derive(Insertable, AsChangeset)]
+#[diesel(table_name = trace_files)]
+pub struct NewTraceFile<'a> {
+    pub path: &'a str,
+    pub object: &'a str,
+    pub size: i64,
+    pub mtime: i64,
+    pub hash: &'a str,
+    pub status: &'a str,
+    pub raw_events: bool,
+    pub aggregates: &'a str,
 }
-    
-impl<'a> InsertTrait for NewInstantiateFunction<'a> {
+
+impl<'a> InsertTrait for NewTraceFile<'a> {
     fn insert(&self, conn: &mut SqliteConnection) -> Result<(), Box<dyn Error + 'static>> {
-        use instantiate_function::{count, duration, name};
-        let _ = diesel::insert_into(instantiate_function::table)
+        use trace_files::path;
+        let _ = diesel::insert_into(trace_files::table)
             .values(self)
-            .on_conflict(name)
+            .on_conflict(path)
             .do_update()
-            .set((count.eq(count + self.count), duration.eq(duration + 1)))
+            .set(self)
             .execute(conn)?;
         Ok(())
     }
 }
 
+/// The compile command an object was built with, from `compile_commands.json`
+#[derive(Queryable)]
+#[diesel(table_name = compile_commands)]
+pub struct CompileCommand {
+    pub object: String,
+    pub source: String,
+    pub output: String,
+    pub directory: String,
+    pub command: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = compile_commands)]
+pub struct NewCompileCommand<'a> {
+    pub object: &'a str,
+    pub source: &'a str,
+    pub output: &'a str,
+    pub directory: &'a str,
+    pub command: &'a str,
+}
+
+/// A step of `.ninja_log`, with the object or the link it builds if it was traced
+#[derive(Queryable)]
+#[diesel(table_name = ninja_steps)]
+pub struct NinjaStep {
+    pub output: String,
+    /// Milliseconds since ninja started
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub mtime: i64,
+    pub command_hash: String,
+    pub object: Option<String>,
+    /// Index of the build of the log which ran the step, see [`crate::ninja_log::Entry::build`]
+    pub build: i64,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = ninja_steps)]
+pub struct NewNinjaStep<'a> {
+    pub output: &'a str,
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub mtime: i64,
+    pub command_hash: &'a str,
+    pub object: Option<&'a str>,
+    pub build: i64,
+}
+
+/// A link traced with `--time-trace`, stored apart from the objects
+#[derive(Queryable)]
+#[diesel(table_name = links)]
+pub struct Link {
+    pub path: String,
+    pub total_time: i32,
+    pub partial: bool,
+    /// When the link started and ended, in microseconds since the UNIX epoch
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = links)]
+pub struct NewLink<'a> {
+    pub path: &'a str,
+    pub total_time: i32,
+    pub partial: bool,
+    pub start_time: Option<i64>,
+    pub end_time: Option<i64>,
+}
+
+/// Time spent in a phase of a link, keyed by the event name
+#[derive(Queryable)]
+#[diesel(table_name = link_phases)]
+pub struct LinkPhase {
+    pub link: String,
+    pub name: String,
+    pub duration: i32,
+    pub count: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = link_phases)]
+pub struct NewLinkPhase<'a> {
+    pub link: &'a str,
+    pub name: &'a str,
+    pub duration: i32,
+    pub count: i32,
+}
+
+/// Time the ThinLTO backend spent on a module of a link
+#[derive(Queryable)]
+#[diesel(table_name = thinlto_modules)]
+pub struct ThinLtoModule {
+    pub link: String,
+    pub module: String,
+    pub duration: i32,
+    pub count: i32,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = thinlto_modules)]
+pub struct NewThinLtoModule<'a> {
+    pub link: &'a str,
+    pub module: &'a str,
+    pub duration: i32,
+    pub count: i32,
+}
+
+/// A unit of `cargo build --timings`, times in microseconds
+#[derive(Queryable)]
+#[diesel(table_name = cargo_units)]
+pub struct CargoUnit {
+    pub name: String,
+    pub version: String,
+    pub target: String,
+    pub mode: String,
+    /// Since cargo started, `None` when imported from the JSON messages
+    pub start_time: Option<i64>,
+    pub duration: i64,
+    /// When the metadata was ready, relative to the start of the unit
+    pub rmeta_time: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = cargo_units)]
+pub struct NewCargoUnit<'a> {
+    pub name: &'a str,
+    pub version: &'a str,
+    pub target: &'a str,
+    pub mode: &'a str,
+    pub start_time: Option<i64>,
+    pub duration: i64,
+    pub rmeta_time: Option<i64>,
+}
+
+/// A row of the GCC `-ftime-report` of an object, times in microseconds
+#[derive(Queryable)]
+#[diesel(table_name = gcc_passes)]
+pub struct GccPass {
+    pub object: String,
+    pub name: String,
+    pub usr: i32,
+    pub sys: i32,
+    pub wall: i32,
+    /// Memory collected, in kB
+    pub ggc: Option<i64>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = gcc_passes)]
+pub struct NewGccPass<'a> {
+    pub object: &'a str,
+    pub name: &'a str,
+    pub usr: i32,
+    pub sys: i32,
+    pub wall: i32,
+    pub ggc: Option<i64>,
+}